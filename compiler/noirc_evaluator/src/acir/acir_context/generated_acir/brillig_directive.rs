@@ -1,3 +1,5 @@
+use std::cell::OnceCell;
+
 use acvm::acir::{
     AcirField,
     brillig::{
@@ -20,6 +22,10 @@ pub(crate) struct BrilligStdLib<F> {
     pub(crate) invert: GeneratedBrillig<F>,
     pub(crate) quotient: GeneratedBrillig<F>,
     pub(crate) to_le_bytes: GeneratedBrillig<F>,
+    /// Only generated once first requested, as few programs make use of it.
+    pub(crate) gcd: OnceCell<GeneratedBrillig<F>>,
+    /// Only generated once first requested, as few programs make use of it.
+    pub(crate) leading_zeros: OnceCell<GeneratedBrillig<F>>,
 }
 
 impl<F: AcirField> Default for BrilligStdLib<F> {
@@ -28,6 +34,8 @@ impl<F: AcirField> Default for BrilligStdLib<F> {
             invert: directive_invert(),
             quotient: directive_quotient(),
             to_le_bytes: directive_to_radix(),
+            gcd: OnceCell::new(),
+            leading_zeros: OnceCell::new(),
         }
     }
 }
@@ -38,6 +46,10 @@ impl<F: AcirField> BrilligStdLib<F> {
             BrilligStdlibFunc::Inverse => &self.invert,
             BrilligStdlibFunc::Quotient => &self.quotient,
             BrilligStdlibFunc::ToLeBytes => &self.to_le_bytes,
+            BrilligStdlibFunc::Gcd => self.gcd.get_or_init(directive_gcd),
+            BrilligStdlibFunc::LeadingZeros => {
+                self.leading_zeros.get_or_init(directive_leading_zeros)
+            }
         }
     }
 }
@@ -47,6 +59,8 @@ pub enum BrilligStdlibFunc {
     Inverse,
     Quotient,
     ToLeBytes,
+    Gcd,
+    LeadingZeros,
 }
 
 /// Generates brillig bytecode which computes the inverse of its input if not null, and zero else.
@@ -315,3 +329,218 @@ pub(crate) fn directive_to_radix<F: AcirField>() -> GeneratedBrillig<F> {
 
     GeneratedBrillig { byte_code, name: "directive_to_radix".to_string(), ..Default::default() }
 }
+
+/// Generates brillig bytecode which computes the GCD of `a` and `b` together with Bezout coefficients
+/// `x` and `y` such that `a*x + b*y == gcd(a, b)`, using the extended Euclidean algorithm.
+///
+/// This is equivalent to the Noir (pseudo)code
+///
+/// ```text
+/// fn gcd(a: Field, b: Field) -> (Field, Field, Field) {
+///    let (mut old_r, mut r) = (a, b);
+///    let (mut old_s, mut s) = (1, 0);
+///    let (mut old_t, mut t) = (0, 1);
+///    while r != 0 {
+///        let q = old_r / r; // integer division
+///        (old_r, r) = (r, old_r - q*r);
+///        (old_s, s) = (s, old_s - q*s);
+///        (old_t, t) = (t, old_t - q*t);
+///    }
+///    (old_r, old_s, old_t)
+/// }
+/// ```
+///
+/// The coefficients may be negative, in which case they wrap around the field modulus.
+pub(crate) fn directive_gcd<F: AcirField>() -> GeneratedBrillig<F> {
+    let old_r = MemoryAddress::direct(0);
+    let r = MemoryAddress::direct(1);
+    let old_s = MemoryAddress::direct(2);
+    let s = MemoryAddress::direct(3);
+    let old_t = MemoryAddress::direct(4);
+    let t = MemoryAddress::direct(5);
+    let quotient = MemoryAddress::direct(6);
+    let temp = MemoryAddress::direct(7);
+    let zero_const = MemoryAddress::direct(8);
+    let r_is_zero = MemoryAddress::direct(9);
+    let size_usize = MemoryAddress::direct(10);
+    let zero_usize = MemoryAddress::direct(11);
+    // Location of the loop condition
+    let loop_location = 8;
+    // Location of the first opcode after the loop
+    let end_location = 24;
+
+    // Generates the opcodes for `(old, new) = (new, old - quotient * new)`
+    let step = |old: MemoryAddress, new: MemoryAddress| {
+        [
+            BrilligOpcode::BinaryFieldOp {
+                op: BinaryFieldOp::Mul,
+                lhs: quotient,
+                rhs: new,
+                destination: temp,
+            },
+            BrilligOpcode::BinaryFieldOp {
+                op: BinaryFieldOp::Sub,
+                lhs: old,
+                rhs: temp,
+                destination: temp,
+            },
+            BrilligOpcode::Mov { destination: old, source: new },
+            BrilligOpcode::Mov { destination: new, source: temp },
+        ]
+    };
+
+    let mut byte_code = vec![
+        BrilligOpcode::Const {
+            destination: size_usize,
+            bit_size: BitSize::Integer(IntegerBitSize::U32),
+            value: F::from(2_usize),
+        },
+        BrilligOpcode::Const {
+            destination: zero_usize,
+            bit_size: BitSize::Integer(IntegerBitSize::U32),
+            value: F::from(0_usize),
+        },
+        // `a` is copied into (0) and `b` into (1)
+        BrilligOpcode::CalldataCopy {
+            destination_address: old_r,
+            size_address: size_usize,
+            offset_address: zero_usize,
+        },
+        BrilligOpcode::Const { destination: old_s, bit_size: BitSize::Field, value: F::one() },
+        BrilligOpcode::Const { destination: s, bit_size: BitSize::Field, value: F::zero() },
+        BrilligOpcode::Const { destination: old_t, bit_size: BitSize::Field, value: F::zero() },
+        BrilligOpcode::Const { destination: t, bit_size: BitSize::Field, value: F::one() },
+        BrilligOpcode::Const {
+            destination: zero_const,
+            bit_size: BitSize::Field,
+            value: F::zero(),
+        },
+        // loop label: exit the loop once `r` is zero
+        BrilligOpcode::BinaryFieldOp {
+            op: BinaryFieldOp::Equals,
+            lhs: r,
+            rhs: zero_const,
+            destination: r_is_zero,
+        },
+        BrilligOpcode::JumpIf { condition: r_is_zero, location: end_location },
+        BrilligOpcode::BinaryFieldOp {
+            op: BinaryFieldOp::IntegerDiv, // We want integer division, not field division!
+            lhs: old_r,
+            rhs: r,
+            destination: quotient,
+        },
+    ];
+    byte_code.extend(step(old_r, r));
+    byte_code.extend(step(old_s, s));
+    byte_code.extend(step(old_t, t));
+    byte_code.push(BrilligOpcode::Jump { location: loop_location });
+    assert_eq!(byte_code.len(), end_location);
+
+    // Move the results into (0), (1) and (2) so they can be returned together
+    byte_code.extend([
+        BrilligOpcode::Mov { destination: MemoryAddress::direct(1), source: old_s },
+        BrilligOpcode::Mov { destination: MemoryAddress::direct(2), source: old_t },
+        BrilligOpcode::Const {
+            destination: size_usize,
+            bit_size: BitSize::Integer(IntegerBitSize::U32),
+            value: F::from(3_usize),
+        },
+        BrilligOpcode::Stop { return_data: HeapVector { pointer: zero_usize, size: size_usize } },
+    ]);
+
+    GeneratedBrillig { byte_code, name: "directive_gcd".to_string(), ..Default::default() }
+}
+
+/// Generates brillig bytecode which counts the leading zeros of `x` as a `bit_size`-bit integer.
+///
+/// This is equivalent to the Noir (pseudo)code
+///
+/// ```text
+/// fn leading_zeros(x: Field, bit_size: Field) -> Field {
+///    let mut count = bit_size;
+///    while x != 0 {
+///        x = x / 2; // integer division
+///        count = count - 1;
+///    }
+///    count
+/// }
+/// ```
+pub(crate) fn directive_leading_zeros<F: AcirField>() -> GeneratedBrillig<F> {
+    let x = MemoryAddress::direct(0);
+    let count = MemoryAddress::direct(1);
+    let zero_const = MemoryAddress::direct(2);
+    let one_const = MemoryAddress::direct(3);
+    let two_const = MemoryAddress::direct(4);
+    let x_is_zero = MemoryAddress::direct(5);
+    let size_usize = MemoryAddress::direct(6);
+    let zero_usize = MemoryAddress::direct(7);
+    // Location of the loop condition
+    let loop_location = 6;
+    // Location of the first opcode after the loop
+    let end_location = 11;
+
+    let byte_code = vec![
+        BrilligOpcode::Const {
+            destination: size_usize,
+            bit_size: BitSize::Integer(IntegerBitSize::U32),
+            value: F::from(2_usize),
+        },
+        BrilligOpcode::Const {
+            destination: zero_usize,
+            bit_size: BitSize::Integer(IntegerBitSize::U32),
+            value: F::from(0_usize),
+        },
+        // `x` is copied into (0) and `bit_size` into (1), where it is used as the initial count
+        BrilligOpcode::CalldataCopy {
+            destination_address: x,
+            size_address: size_usize,
+            offset_address: zero_usize,
+        },
+        BrilligOpcode::Const {
+            destination: zero_const,
+            bit_size: BitSize::Field,
+            value: F::zero(),
+        },
+        BrilligOpcode::Const { destination: one_const, bit_size: BitSize::Field, value: F::one() },
+        BrilligOpcode::Const {
+            destination: two_const,
+            bit_size: BitSize::Field,
+            value: F::from(2_u128),
+        },
+        // loop label: exit the loop once `x` is zero
+        BrilligOpcode::BinaryFieldOp {
+            op: BinaryFieldOp::Equals,
+            lhs: x,
+            rhs: zero_const,
+            destination: x_is_zero,
+        },
+        BrilligOpcode::JumpIf { condition: x_is_zero, location: end_location },
+        BrilligOpcode::BinaryFieldOp {
+            op: BinaryFieldOp::IntegerDiv, // We want integer division, not field division!
+            lhs: x,
+            rhs: two_const,
+            destination: x,
+        },
+        BrilligOpcode::BinaryFieldOp {
+            op: BinaryFieldOp::Sub,
+            lhs: count,
+            rhs: one_const,
+            destination: count,
+        },
+        BrilligOpcode::Jump { location: loop_location },
+        // Move the count into (0) so it can be returned
+        BrilligOpcode::Mov { destination: x, source: count },
+        BrilligOpcode::Const {
+            destination: size_usize,
+            bit_size: BitSize::Integer(IntegerBitSize::U32),
+            value: F::from(1_usize),
+        },
+        BrilligOpcode::Stop { return_data: HeapVector { pointer: zero_usize, size: size_usize } },
+    ];
+
+    GeneratedBrillig {
+        byte_code,
+        name: "directive_leading_zeros".to_string(),
+        ..Default::default()
+    }
+}
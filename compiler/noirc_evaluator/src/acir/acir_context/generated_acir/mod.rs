@@ -657,6 +657,19 @@ impl<F: AcirField> GeneratedAcir<F> {
         OpcodeLocation::Acir(self.opcodes.len() - 1)
     }
 
+    /// Returns the location of each opcode paired with the call stack it was emitted under, in opcode order.
+    ///
+    /// Opcodes emitted without a call stack are omitted. The call stacks can be resolved using `call_stacks`.
+    pub(crate) fn opcode_call_stacks(&self) -> Vec<(OpcodeLocation, CallStackId)> {
+        let mut opcode_call_stacks: Vec<_> = self
+            .location_map
+            .iter()
+            .map(|(location, call_stack_id)| (*location, *call_stack_id))
+            .collect();
+        opcode_call_stacks.sort_by_key(|(location, _)| *location);
+        opcode_call_stacks
+    }
+
     pub(crate) fn record_error_type(&mut self, selector: ErrorSelector, typ: ErrorType) {
         self.error_types.insert(selector, typ);
     }
@@ -19,7 +19,7 @@ use acvm::{
         native_types::{Expression, Witness},
     },
 };
-use fxhash::FxHashMap as HashMap;
+use fxhash::{FxHashMap as HashMap, FxHashSet as HashSet};
 use iter_extended::{try_vecmap, vecmap};
 use noirc_errors::call_stack::{CallStack, CallStackHelper};
 use num_bigint::BigUint;
@@ -36,6 +36,8 @@ mod big_int;
 mod black_box;
 mod brillig_call;
 mod generated_acir;
+#[cfg(test)]
+mod tests;
 
 use super::{
     AcirDynamicArray, AcirValue,
@@ -58,6 +60,17 @@ pub(crate) struct AcirContext<F: AcirField, B: BlackBoxFunctionSolver<F>> {
 
     constant_witnesses: HashMap<F, Witness>,
 
+    /// Variables which have already been constrained to be boolean by [`AcirContext::assert_is_bool`].
+    proven_booleans: HashSet<AcirVar>,
+
+    /// The smallest bit size each witness has been range checked to by [`AcirContext::range_constrain_var`],
+    /// along with the assertion message attached to that range check.
+    proven_bit_size: HashMap<Witness, (u32, Option<String>)>,
+
+    /// The products of pairs of witness variables, keyed by the ordered pair of operands,
+    /// so that multiplying the same witnesses again reuses the existing product.
+    witness_products: HashMap<(AcirVar, AcirVar), AcirVar>,
+
     /// An in-memory representation of ACIR.
     ///
     /// This struct will progressively be populated
@@ -82,6 +95,9 @@ impl<F: AcirField, B: BlackBoxFunctionSolver<F>> AcirContext<F, B> {
             blackbox_solver,
             vars: Default::default(),
             constant_witnesses: Default::default(),
+            proven_booleans: Default::default(),
+            proven_bit_size: Default::default(),
+            witness_products: Default::default(),
             acir_ir: Default::default(),
             big_int_ctx: Default::default(),
             expression_width: Default::default(),
@@ -387,6 +403,10 @@ impl<F: AcirField, B: BlackBoxFunctionSolver<F>> AcirContext<F, B> {
             let prod = self.mul_var(lhs, rhs)?;
             let sum = self.add_var(lhs, rhs)?;
             self.add_mul_var(sum, -F::from(2_u128), prod)
+        } else if let Some(result) =
+            fold_bitwise_constants(&lhs_expr, &rhs_expr, bit_size, |lhs, rhs| lhs ^ rhs)
+        {
+            Ok(self.add_constant(result))
         } else {
             let inputs = vec![AcirValue::Var(lhs, typ.clone()), AcirValue::Var(rhs, typ)];
             let outputs = self.black_box_function(BlackBoxFunc::XOR, inputs, 1)?;
@@ -417,6 +437,10 @@ impl<F: AcirField, B: BlackBoxFunctionSolver<F>> AcirContext<F, B> {
         if bit_size == 1 {
             // Operands are booleans.
             self.mul_var(lhs, rhs)
+        } else if let Some(result) =
+            fold_bitwise_constants(&lhs_expr, &rhs_expr, bit_size, |lhs, rhs| lhs & rhs)
+        {
+            Ok(self.add_constant(result))
         } else {
             let inputs = vec![AcirValue::Var(lhs, typ.clone()), AcirValue::Var(rhs, typ)];
             let outputs = self.black_box_function(BlackBoxFunc::AND, inputs, 1)?;
@@ -448,6 +472,10 @@ impl<F: AcirField, B: BlackBoxFunctionSolver<F>> AcirContext<F, B> {
             let mul = self.mul_var(lhs, rhs)?;
             let sum = self.add_var(lhs, rhs)?;
             self.sub_var(sum, mul)
+        } else if let Some(result) =
+            fold_bitwise_constants(&lhs_expr, &rhs_expr, bit_size, |lhs, rhs| lhs | rhs)
+        {
+            Ok(self.add_constant(result))
         } else {
             // Implement OR in terms of AND
             // (NOT a) AND (NOT b) => NOT (a OR b)
@@ -590,7 +618,7 @@ impl<F: AcirField, B: BlackBoxFunctionSolver<F>> AcirContext<F, B> {
             }
             NumericType::Unsigned { bit_size } => {
                 let (quotient_var, _remainder_var) =
-                    self.euclidean_division_var(lhs, rhs, bit_size, predicate)?;
+                    self.unsigned_division_var(lhs, rhs, bit_size, predicate)?;
                 Ok(quotient_var)
             }
             NumericType::Signed { bit_size } => {
@@ -633,9 +661,15 @@ impl<F: AcirField, B: BlackBoxFunctionSolver<F>> AcirContext<F, B> {
                 self.add_data(AcirVarData::from(&expr * constant))
             }
             (AcirVarData::Witness(lhs_witness), AcirVarData::Witness(rhs_witness)) => {
+                let key = (lhs.min(rhs), lhs.max(rhs));
+                if let Some(product) = self.witness_products.get(&key) {
+                    return Ok(*product);
+                }
                 let mut expr = Expression::default();
                 expr.push_multiplication_term(F::one(), lhs_witness, rhs_witness);
-                self.add_data(AcirVarData::Expr(expr))
+                let product = self.add_data(AcirVarData::Expr(expr));
+                self.witness_products.insert(key, product);
+                product
             }
             (AcirVarData::Expr(expression), AcirVarData::Witness(witness))
             | (AcirVarData::Witness(witness), AcirVarData::Expr(expression))
@@ -770,6 +804,52 @@ impl<F: AcirField, B: BlackBoxFunctionSolver<F>> AcirContext<F, B> {
         self.sub_var(max, x)
     }
 
+    /// Returns the quotient and remainder of the unsigned division of `lhs` by `rhs`.
+    ///
+    /// Dividing by a constant power of two splits `lhs` into its high and low bits, so rather than
+    /// using a brillig hint the remainder is taken to be the low bits of `lhs` (zeroed by the predicate).
+    /// The AND opcode requires its inputs to fit in `bit_size` bits, which ensures that the
+    /// quotient `(lhs - r) / rhs` is an integer of at most `bit_size - log2(rhs)` bits.
+    /// The remainder is explicitly range constrained to `log2(rhs)` bits so that `r < rhs` holds
+    /// however the AND is lowered.
+    /// Any other division is performed by [`AcirContext::euclidean_division_var`].
+    fn unsigned_division_var(
+        &mut self,
+        lhs: AcirVar,
+        rhs: AcirVar,
+        bit_size: u32,
+        predicate: AcirVar,
+    ) -> Result<(AcirVar, AcirVar), RuntimeError> {
+        let lhs_is_constant = self.var_to_expression(lhs)?.is_const();
+        if let Some(rhs_const) = self.var_to_expression(rhs)?.to_const() {
+            if !lhs_is_constant
+                && bit_size <= 128
+                && rhs_const.num_bits() <= bit_size
+                && rhs_const.to_u128() > 1
+                && rhs_const.to_u128().is_power_of_two()
+            {
+                let remainder_bit_size = rhs_const.to_u128().trailing_zeros();
+                let rhs_inverse = self.add_constant(rhs_const.inverse());
+                let mask = self.add_constant(*rhs_const - F::one());
+                let one = self.add_constant(F::one());
+                let lhs = self.mul_var(lhs, predicate)?;
+                let remainder_var = self.and_var(lhs, mask, AcirType::unsigned(bit_size))?;
+                // Constrain `r < rhs`.
+                self.range_constrain_var(
+                    remainder_var,
+                    &NumericType::Unsigned { bit_size: remainder_bit_size },
+                    None,
+                    one,
+                )?;
+                let high_bits = self.sub_var(lhs, remainder_var)?;
+                let quotient_var = self.mul_var(high_bits, rhs_inverse)?;
+                return Ok((quotient_var, remainder_var));
+            }
+        }
+
+        self.euclidean_division_var(lhs, rhs, bit_size, predicate)
+    }
+
     /// Returns the quotient and remainder such that lhs = rhs * quotient + remainder
     fn euclidean_division_var(
         &mut self,
@@ -974,20 +1054,25 @@ impl<F: AcirField, B: BlackBoxFunctionSolver<F>> AcirContext<F, B> {
                     rhs_const.num_bits(),
                     predicate,
                 )?;
-            } else if bit_size == 128 {
-                // q and b are u128 and q*b could overflow so we check that either q or b are less than 2^64
-                let two_pow_64: F = power_of_two(64);
-                let two_pow_64 = self.add_constant(two_pow_64);
+            } else if bit_size + bit_size.div_ceil(2) < F::max_num_bits() - 1 {
+                // q*b could overflow so we check that either q or b are less than 2^half, where half
+                // is half of the bit size rounded up. Then q*b+r < 2^(bit_size+half+1) can't overflow.
+                let half_bit_size = bit_size.div_ceil(2);
+                let two_pow_half: F = power_of_two(half_bit_size);
+                let two_pow_half = self.add_constant(two_pow_half);
 
                 let (q_upper, _) =
-                    self.euclidean_division_var(quotient_var, two_pow_64, bit_size, predicate)?;
+                    self.euclidean_division_var(quotient_var, two_pow_half, bit_size, predicate)?;
                 let (rhs_upper, _) =
-                    self.euclidean_division_var(rhs, two_pow_64, bit_size, predicate)?;
+                    self.euclidean_division_var(rhs, two_pow_half, bit_size, predicate)?;
                 let mul_uppers = self.mul_var(q_upper, rhs_upper)?;
                 self.assert_eq_var(mul_uppers, zero, None)?;
             } else {
                 // we do not support unbounded division
-                unreachable!("overflow in unbounded division");
+                return Err(RuntimeError::UnsupportedDivisionBitSize {
+                    num_bits: bit_size,
+                    call_stack: self.get_call_stack(),
+                });
             }
         }
 
@@ -1177,7 +1262,7 @@ impl<F: AcirField, B: BlackBoxFunctionSolver<F>> AcirContext<F, B> {
             NumericType::Signed { bit_size } => {
                 self.signed_division_var(lhs, rhs, bit_size, predicate)?
             }
-            _ => self.euclidean_division_var(lhs, rhs, bit_size, predicate)?,
+            _ => self.unsigned_division_var(lhs, rhs, bit_size, predicate)?,
         };
         Ok(remainder_var)
     }
@@ -1185,6 +1270,9 @@ impl<F: AcirField, B: BlackBoxFunctionSolver<F>> AcirContext<F, B> {
     /// Constrains the `AcirVar` variable to be of type `NumericType`.
     ///
     /// If `predicate` is false, the constrain will never fail.
+    ///
+    /// The range check is skipped if the variable has already been range checked to at most the same
+    /// number of bits, unless `message` differs from the message attached to that earlier range check.
     pub(crate) fn range_constrain_var(
         &mut self,
         variable: AcirVar,
@@ -1204,9 +1292,23 @@ impl<F: AcirField, B: BlackBoxFunctionSolver<F>> AcirContext<F, B> {
                 // Under a predicate, a range check must not fail, so we
                 // range check `predicate * variable` instead.
                 let predicate_range = self.mul_var(variable, predicate)?;
+
+                // If the witness has already been range checked to at most `bit_size` bits then
+                // this range check is redundant, as long as a failure would report the same message.
+                if let Some(witness) = self.var_to_expression(predicate_range)?.to_witness() {
+                    if let Some((proven, proven_message)) = self.proven_bit_size.get(&witness) {
+                        if proven <= bit_size && (message.is_none() || message == *proven_message) {
+                            return Ok(predicate_range);
+                        }
+                    }
+                }
+
                 let witness_var = self.get_or_create_witness_var(predicate_range)?;
                 let witness = self.var_to_witness(witness_var)?;
                 self.acir_ir.range_constraint(witness, *bit_size)?;
+                if self.proven_bit_size.get(&witness).is_none_or(|(proven, _)| bit_size <= proven) {
+                    self.proven_bit_size.insert(witness, (*bit_size, message.clone()));
+                }
                 if let Some(message) = message {
                     let payload = self.generate_assertion_message_payload(message.clone());
                     self.acir_ir
@@ -1253,10 +1355,33 @@ impl<F: AcirField, B: BlackBoxFunctionSolver<F>> AcirContext<F, B> {
         rhs: AcirVar,
         bit_count: u32,
     ) -> Result<AcirVar, RuntimeError> {
-        let pow_last = self.add_constant(F::from(1_u128 << (bit_count - 1)));
         let pow = self.add_constant(F::from(1_u128 << (bit_count)));
+        let same_sign = self.sign_bits_xor(lhs, rhs, bit_count)?;
 
-        // We check whether the inputs have same sign or not by computing the XOR of their bit sign
+        // We compute the input difference
+        let no_underflow = self.add_var(lhs, pow)?;
+        let diff = self.sub_var(no_underflow, rhs)?;
+
+        // We check the 'bit sign' of the difference
+        let diff_sign = self.less_than_var(diff, pow, bit_count + 1)?;
+
+        // Then the result is simply diff_sign XOR same_sign (can be checked with a truth table)
+        self.xor_var(
+            diff_sign,
+            same_sign,
+            AcirType::NumericType(NumericType::Signed { bit_size: 1 }),
+        )
+    }
+
+    /// Returns the XOR of the sign bits of the `bit_count`-bit signed integers `lhs` and `rhs`,
+    /// i.e. one if their signs differ.
+    fn sign_bits_xor(
+        &mut self,
+        lhs: AcirVar,
+        rhs: AcirVar,
+        bit_count: u32,
+    ) -> Result<AcirVar, RuntimeError> {
+        let pow_last = self.add_constant(F::from(1_u128 << (bit_count - 1)));
 
         // Predicate is always active as `pow_last` is known to be non-zero.
         let one = self.add_constant(1_u128);
@@ -1272,25 +1397,7 @@ impl<F: AcirField, B: BlackBoxFunctionSolver<F>> AcirContext<F, B> {
             AcirType::NumericType(NumericType::Unsigned { bit_size: bit_count }),
             one,
         )?;
-        let same_sign = self.xor_var(
-            lhs_sign,
-            rhs_sign,
-            AcirType::NumericType(NumericType::Signed { bit_size: 1 }),
-        )?;
-
-        // We compute the input difference
-        let no_underflow = self.add_var(lhs, pow)?;
-        let diff = self.sub_var(no_underflow, rhs)?;
-
-        // We check the 'bit sign' of the difference
-        let diff_sign = self.less_than_var(diff, pow, bit_count + 1)?;
-
-        // Then the result is simply diff_sign XOR same_sign (can be checked with a truth table)
-        self.xor_var(
-            diff_sign,
-            same_sign,
-            AcirType::NumericType(NumericType::Signed { bit_size: 1 }),
-        )
+        self.xor_var(lhs_sign, rhs_sign, AcirType::NumericType(NumericType::Signed { bit_size: 1 }))
     }
 
     /// Returns an `AcirVar` which will be `1` if lhs >= rhs
@@ -1491,6 +1598,31 @@ impl<F: AcirField, B: BlackBoxFunctionSolver<F>> AcirContext<F, B> {
         &mut self,
         block_id: BlockId,
         index: &AcirVar,
+    ) -> Result<AcirVar, InternalError> {
+        self.push_memory_read(block_id, index, None)
+    }
+
+    /// Returns a Variable that is constrained to be the result of reading
+    /// from the memory `block_id` at the given `index`, if `predicate` is true.
+    ///
+    /// When `predicate` is false the read is not performed, so `index` may be out of bounds.
+    pub(crate) fn read_from_memory_with_predicate(
+        &mut self,
+        block_id: BlockId,
+        index: &AcirVar,
+        predicate: AcirVar,
+    ) -> Result<AcirVar, InternalError> {
+        let predicate = self.memory_op_predicate(predicate)?;
+        self.push_memory_read(block_id, index, predicate)
+    }
+
+    /// Adds a memory read from `block_id` at the given `index` under the given opcode predicate,
+    /// returning the Variable holding the value read.
+    fn push_memory_read(
+        &mut self,
+        block_id: BlockId,
+        index: &AcirVar,
+        predicate: Option<Expression<F>>,
     ) -> Result<AcirVar, InternalError> {
         // Fetch the witness corresponding to the index
         let index_var = self.get_or_create_witness_var(*index)?;
@@ -1502,7 +1634,7 @@ impl<F: AcirField, B: BlackBoxFunctionSolver<F>> AcirContext<F, B> {
 
         // Add the memory read operation to the list of opcodes
         let op = MemOp::read_at_mem_index(index_witness.into(), value_read_witness);
-        self.acir_ir.push_opcode(Opcode::MemoryOp { block_id, op, predicate: None });
+        self.acir_ir.push_opcode(Opcode::MemoryOp { block_id, op, predicate });
 
         Ok(value_read_var)
     }
@@ -1513,6 +1645,32 @@ impl<F: AcirField, B: BlackBoxFunctionSolver<F>> AcirContext<F, B> {
         block_id: BlockId,
         index: &AcirVar,
         value: &AcirVar,
+    ) -> Result<(), InternalError> {
+        self.push_memory_write(block_id, index, value, None)
+    }
+
+    /// Constrains the Variable `value` to be the new value located at `index` in the memory `block_id`,
+    /// if `predicate` is true.
+    ///
+    /// When `predicate` is false the write is not performed, so `index` may be out of bounds.
+    pub(crate) fn write_to_memory_with_predicate(
+        &mut self,
+        block_id: BlockId,
+        index: &AcirVar,
+        value: &AcirVar,
+        predicate: AcirVar,
+    ) -> Result<(), InternalError> {
+        let predicate = self.memory_op_predicate(predicate)?;
+        self.push_memory_write(block_id, index, value, predicate)
+    }
+
+    /// Adds a memory write of `value` to `block_id` at the given `index` under the given opcode predicate.
+    fn push_memory_write(
+        &mut self,
+        block_id: BlockId,
+        index: &AcirVar,
+        value: &AcirVar,
+        predicate: Option<Expression<F>>,
     ) -> Result<(), InternalError> {
         // Fetch the witness corresponding to the index
         let index_var = self.get_or_create_witness_var(*index)?;
@@ -1524,11 +1682,25 @@ impl<F: AcirField, B: BlackBoxFunctionSolver<F>> AcirContext<F, B> {
 
         // Add the memory write operation to the list of opcodes
         let op = MemOp::write_to_mem_index(index_witness.into(), value_write_witness.into());
-        self.acir_ir.push_opcode(Opcode::MemoryOp { block_id, op, predicate: None });
+        self.acir_ir.push_opcode(Opcode::MemoryOp { block_id, op, predicate });
 
         Ok(())
     }
 
+    /// Returns the predicate expression to attach to a memory operation.
+    ///
+    /// A constant one predicate is omitted, as memory operations without a predicate are always performed.
+    fn memory_op_predicate(
+        &self,
+        predicate: AcirVar,
+    ) -> Result<Option<Expression<F>>, InternalError> {
+        if self.is_constant_one(&predicate) {
+            Ok(None)
+        } else {
+            Ok(Some(self.var_to_expression(predicate)?))
+        }
+    }
+
     /// Insert the MemoryInit for the Return Data array, using the provided witnesses
     pub(crate) fn initialize_return_data(&mut self, block_id: BlockId, init: Vec<Witness>) {
         self.acir_ir.push_opcode(Opcode::MemoryInit {
@@ -1579,7 +1751,13 @@ impl<F: AcirField, B: BlackBoxFunctionSolver<F>> AcirContext<F, B> {
     ) -> Result<(), InternalError> {
         match input {
             AcirValue::Var(var, _) => {
-                let var = self.get_or_create_witness_var(var)?;
+                // Constants are looked up in `constant_witnesses` by `var_to_witness`, so repeated
+                // constants share a witness without needing a new variable for each element.
+                let var = if self.var_to_expression(var)?.is_const() {
+                    var
+                } else {
+                    self.get_or_create_witness_var(var)?
+                };
                 witnesses.push(self.var_to_witness(var)?);
             }
             AcirValue::Array(values) => {
@@ -1636,84 +1814,1969 @@ impl<F: AcirField, B: BlackBoxFunctionSolver<F>> AcirContext<F, B> {
     }
 }
 
-/// Returns an `F` representing the value `2**power`
-///
-/// # Panics
-///
-/// Panics if `2**power` exceeds `F::modulus()`.
-pub(super) fn power_of_two<F: AcirField>(power: u32) -> F {
-    if power >= F::max_num_bits() {
-        panic!("Field cannot represent this power of two");
+// Gadgets built on top of the core `AcirContext` operations which ACIR-gen doesn't make use of yet.
+#[allow(dead_code)]
+impl<F: AcirField, B: BlackBoxFunctionSolver<F>> AcirContext<F, B> {
+    /// Returns the maximum width of the expressions which will be constrained.
+    pub(crate) fn expression_width(&self) -> ExpressionWidth {
+        self.expression_width
     }
-    let full_bytes = power / 8;
-    let extra_bits = power % 8;
-    let most_significant_byte: u8 = match extra_bits % 8 {
-        0 => 0x01,
-        1 => 0x02,
-        2 => 0x04,
-        3 => 0x08,
-        4 => 0x10,
-        5 => 0x20,
-        6 => 0x40,
-        7 => 0x80,
-        _ => unreachable!("We cover the full range of x % 8"),
-    };
 
-    let bytes_be: Vec<u8> = std::iter::once(most_significant_byte)
-        .chain(std::iter::repeat_n(0, full_bytes as usize))
-        .collect();
+    /// Returns whether the sum of `a` and `b` fits in a single arithmetic identity at the current
+    /// expression width, i.e. whether [`AcirContext::add_var`] can add them without creating new witnesses.
+    pub(crate) fn would_fit_in_one_identity(
+        &self,
+        a: AcirVar,
+        b: AcirVar,
+    ) -> Result<bool, InternalError> {
+        let sum_expr = &self.var_to_expression(a)? + &self.var_to_expression(b)?;
+        Ok(fits_in_one_identity(&sum_expr, self.expression_width))
+    }
 
-    F::from_be_bytes_reduce(&bytes_be)
-}
+    /// Returns the number of opcodes emitted so far.
+    pub(crate) fn opcode_count(&self) -> usize {
+        self.acir_ir.opcodes().len()
+    }
 
-/// Enum representing the possible values that a
-/// Variable can be given.
-#[derive(Debug, Eq, Clone, PartialEq)]
-enum AcirVarData<F> {
-    Witness(Witness),
-    Expr(Expression<F>),
-    Const(F),
-}
+    /// Returns the number of witnesses allocated so far.
+    pub(crate) fn witness_count(&self) -> u32 {
+        // `current_witness_index` is the index of the last allocated witness, if any.
+        self.acir_ir.current_witness_index.map_or(0, |index| index + 1)
+    }
 
-impl<F> AcirVarData<F> {
-    /// Returns a FieldElement, if the underlying `AcirVarData`
-    /// represents a constant.
-    pub(crate) fn as_constant(&self) -> Option<&F> {
-        if let AcirVarData::Const(field) = self {
-            return Some(field);
+    /// Returns the number of opcodes emitted so far, bucketed by the kind of opcode.
+    pub(crate) fn opcode_count_by_kind(&self) -> HashMap<&'static str, usize> {
+        let mut counts = HashMap::default();
+        for opcode in self.acir_ir.opcodes() {
+            let kind = match opcode {
+                Opcode::AssertZero(_) => "AssertZero",
+                Opcode::BlackBoxFuncCall(_) => "BlackBox",
+                Opcode::MemoryOp { .. } => "MemoryOp",
+                Opcode::MemoryInit { .. } => "MemoryInit",
+                Opcode::BrilligCall { .. } => "BrilligCall",
+                Opcode::Call { .. } => "Call",
+            };
+            *counts.entry(kind).or_insert(0) += 1;
         }
-        None
+        counts
     }
-}
 
-impl<F: AcirField> AcirVarData<F> {
-    /// Converts all enum variants to an Expression.
-    pub(crate) fn to_expression(&self) -> Cow<Expression<F>> {
-        match self {
-            AcirVarData::Witness(witness) => Cow::Owned(Expression::from(*witness)),
-            AcirVarData::Expr(expr) => Cow::Borrowed(expr),
-            AcirVarData::Const(constant) => Cow::Owned(Expression::from(*constant)),
+    /// Returns variables constrained to be the inverses of `vars`, as if [`Self::inv_var`] was called
+    /// on each of them, but using a single Brillig inversion for all of the non-constant variables.
+    ///
+    /// This is Montgomery's batch inversion: the inverse of the product of the variables is walked back
+    /// through the prefix products to recover the inverse of each variable. Constants are inverted directly.
+    pub(crate) fn batch_inv_var(
+        &mut self,
+        vars: &[AcirVar],
+        predicate: AcirVar,
+    ) -> Result<Vec<AcirVar>, RuntimeError> {
+        let mut inverses = vars.to_vec();
+        let mut batch = Vec::new();
+        for (i, var) in vars.iter().enumerate() {
+            if matches!(self.vars[var], AcirVarData::Const(_)) {
+                inverses[i] = self.inv_var(*var, predicate)?;
+            } else {
+                batch.push(i);
+            }
+        }
+        if batch.is_empty() {
+            return Ok(inverses);
+        }
+
+        let one = self.add_constant(F::one());
+        let mut prefix_products = Vec::with_capacity(batch.len());
+        let mut product = one;
+        for i in &batch {
+            prefix_products.push(product);
+            product = self.mul_var(product, vars[*i])?;
+        }
+
+        let results = self.stdlib_brillig_call(
+            predicate,
+            BrilligStdlibFunc::Inverse,
+            &self.brillig_stdlib.get_code(BrilligStdlibFunc::Inverse).clone(),
+            vec![AcirValue::Var(product, AcirType::field())],
+            vec![AcirType::field()],
+            true,
+        )?;
+        let mut product_inverse = Self::expect_one_var(results);
+
+        // Walking back, `product_inverse` is the inverse of the product of the variables up to and
+        // including `vars[i]`, so multiplying it by the product of the ones before gives the inverse of `vars[i]`.
+        for (i, prefix_product) in batch.into_iter().zip(prefix_products).rev() {
+            let inverse = self.mul_var(product_inverse, prefix_product)?;
+            product_inverse = self.mul_var(product_inverse, vars[i])?;
+
+            // Check that the inverted var is valid.
+            // This check prevents invalid divisions by zero.
+            let should_be_one = self.mul_var(inverse, vars[i])?;
+            self.maybe_eq_predicate(should_be_one, predicate)?;
+            inverses[i] = inverse;
         }
+        Ok(inverses)
     }
-}
 
-impl<F> From<Witness> for AcirVarData<F> {
-    fn from(witness: Witness) -> Self {
-        AcirVarData::Witness(witness)
+    /// Adds a new Variable to context whose value will be constrained to be the inverse of `var`,
+    /// or one if `var` is zero.
+    ///
+    /// Unlike [`Self::inv_var`] this never fails: zero is inverted as `var + is_zero(var)`, which
+    /// is the invertible value one, and the result of that inversion is then replaced by one.
+    pub(crate) fn inv_or_one(
+        &mut self,
+        var: AcirVar,
+        predicate: AcirVar,
+    ) -> Result<AcirVar, RuntimeError> {
+        let zero = self.add_constant(F::zero());
+        let one = self.add_constant(F::one());
+        let is_zero = self.eq_var(var, zero)?;
+        let invertible = self.add_var(var, is_zero)?;
+        let inverted_var = self.inv_var(invertible, predicate)?;
+        self.select_var(is_zero, one, inverted_var)
     }
-}
 
-impl<F: AcirField> From<Expression<F>> for AcirVarData<F> {
-    fn from(expr: Expression<F>) -> Self {
-        // Prefer simpler variants if possible.
-        if let Some(constant) = expr.to_const() {
-            AcirVarData::Const(*constant)
-        } else if let Some(witness) = expr.to_witness() {
-            AcirVarData::from(witness)
-        } else {
-            AcirVarData::Expr(expr)
+    /// Constrains `var` to be either zero or one, by asserting that `var * var - var == 0`.
+    ///
+    /// Variables which have already been constrained by this method are not constrained again.
+    pub(crate) fn assert_is_bool(&mut self, var: AcirVar) -> Result<(), RuntimeError> {
+        if let Some(constant) = self.var_to_expression(var)?.to_const() {
+            if constant.is_zero() || constant.is_one() {
+                return Ok(());
+            }
+            // Constraint is always false
+            let call_stack = self.get_call_stack();
+            self.warnings
+                .push(SsaReport::Bug(InternalBug::AssertFailed { call_stack, message: None }));
+        } else if self.proven_booleans.contains(&var) {
+            return Ok(());
+        }
+
+        let square = self.mul_var(var, var)?;
+        let square_minus_var = self.sub_var(square, var)?;
+        let expr = self.var_to_expression(square_minus_var)?;
+        self.acir_ir.assert_is_zero(expr);
+        self.proven_booleans.insert(var);
+        Ok(())
+    }
+
+    /// Adds a new variable that is constrained to be `base` raised to the constant `exponent`.
+    ///
+    /// The power is computed by repeated squaring, requiring at most `2 * log2(exponent)` multiplications.
+    pub(crate) fn pow_var(
+        &mut self,
+        base: AcirVar,
+        exponent: u32,
+    ) -> Result<AcirVar, RuntimeError> {
+        if let Some(constant) = self.var_to_expression(base)?.to_const() {
+            return Ok(self.add_constant(constant.pow(&F::from(exponent))));
+        }
+        match exponent {
+            0 => return Ok(self.add_constant(F::one())),
+            1 => return Ok(base),
+            _ => (),
         }
+
+        let mut result = None;
+        let mut square = base;
+        let mut remaining = exponent;
+        loop {
+            if remaining & 1 == 1 {
+                result = Some(match result {
+                    Some(result) => self.mul_var(result, square)?,
+                    None => square,
+                });
+            }
+            remaining >>= 1;
+            if remaining == 0 {
+                break;
+            }
+            square = self.mul_var(square, square)?;
+        }
+        Ok(result.expect("exponent is non-zero"))
+    }
+
+    /// Adds a new variable that is constrained to be the linear combination `weights[0] * vars[0] + ... + weights[n] * vars[n]`.
+    ///
+    /// Terms with a zero weight are skipped and terms with a unit weight are added without a multiplication.
+    pub(crate) fn weighted_sum(
+        &mut self,
+        weights: &[F],
+        vars: &[AcirVar],
+    ) -> Result<AcirVar, RuntimeError> {
+        if weights.len() != vars.len() {
+            return Err(RuntimeError::InternalError(InternalError::General {
+                message: format!(
+                    "cannot combine {} variables with {} weights",
+                    vars.len(),
+                    weights.len()
+                ),
+                call_stack: self.get_call_stack(),
+            }));
+        }
+
+        let mut sum = self.add_constant(F::zero());
+        for (weight, var) in weights.iter().zip(vars) {
+            if weight.is_zero() {
+                continue;
+            }
+            sum = if weight.is_one() {
+                self.add_var(sum, *var)?
+            } else {
+                self.add_mul_var(sum, *weight, *var)?
+            };
+        }
+        Ok(sum)
+    }
+
+    /// Adds a new variable that is constrained to be `then_var` if `cond` is true and `else_var` otherwise.
+    ///
+    /// `cond` is assumed to be boolean. No constraints are added if `cond` is constant or if both
+    /// branches are the same expression.
+    pub(crate) fn select_var(
+        &mut self,
+        cond: AcirVar,
+        then_var: AcirVar,
+        else_var: AcirVar,
+    ) -> Result<AcirVar, RuntimeError> {
+        if let Some(cond_const) = self.var_to_expression(cond)?.to_const() {
+            return Ok(if cond_const.is_zero() { else_var } else { then_var });
+        }
+        if self.var_to_expression(then_var)? == self.var_to_expression(else_var)? {
+            return Ok(else_var);
+        }
+
+        // `else + cond * (then - else)`
+        let diff = self.sub_var(then_var, else_var)?;
+        let selected_diff = self.mul_var(cond, diff)?;
+        self.add_var(else_var, selected_diff)
+    }
+
+    /// Adds a new variable that is constrained to be `value` if `reset` is true and `acc + value` otherwise.
+    ///
+    /// This allows running aggregates to be restarted at segment boundaries. `reset` is assumed to be boolean.
+    pub(crate) fn accumulate_or_reset(
+        &mut self,
+        acc: AcirVar,
+        value: AcirVar,
+        reset: AcirVar,
+    ) -> Result<AcirVar, RuntimeError> {
+        if let Some(reset_const) = self.var_to_expression(reset)?.to_const() {
+            return if reset_const.is_zero() { self.add_var(acc, value) } else { Ok(value) };
+        }
+
+        let accumulated = self.add_var(acc, value)?;
+        self.select_var(reset, value, accumulated)
     }
+
+    /// Returns the larger of the integers `lhs` and `rhs`, compared according to their numeric type `typ`.
+    pub(crate) fn max_var(
+        &mut self,
+        lhs: AcirVar,
+        rhs: AcirVar,
+        typ: AcirType,
+    ) -> Result<AcirVar, RuntimeError> {
+        let lhs_is_less = match typ.to_numeric_type() {
+            NumericType::Signed { bit_size } => self.less_than_signed(lhs, rhs, bit_size)?,
+            NumericType::Unsigned { bit_size } => self.less_than_var(lhs, rhs, bit_size)?,
+            NumericType::NativeField => {
+                return Err(RuntimeError::InternalError(InternalError::General {
+                    message: "cannot compute the maximum of field elements".to_string(),
+                    call_stack: self.get_call_stack(),
+                }));
+            }
+        };
+        self.select_var(lhs_is_less, rhs, lhs)
+    }
+
+    /// Returns the smaller of the integers `lhs` and `rhs`, compared according to their numeric type `typ`.
+    pub(crate) fn min_var(
+        &mut self,
+        lhs: AcirVar,
+        rhs: AcirVar,
+        typ: AcirType,
+    ) -> Result<AcirVar, RuntimeError> {
+        let lhs_is_less = match typ.to_numeric_type() {
+            NumericType::Signed { bit_size } => self.less_than_signed(lhs, rhs, bit_size)?,
+            NumericType::Unsigned { bit_size } => self.less_than_var(lhs, rhs, bit_size)?,
+            NumericType::NativeField => {
+                return Err(RuntimeError::InternalError(InternalError::General {
+                    message: "cannot compute the minimum of field elements".to_string(),
+                    call_stack: self.get_call_stack(),
+                }));
+            }
+        };
+        self.select_var(lhs_is_less, lhs, rhs)
+    }
+
+    /// Returns `index` clamped to the valid indices of an array of length `len`, i.e. `min(index, len - 1)`,
+    /// where `index` and `len - 1` both fit in `bits` bits.
+    ///
+    /// Returns zero when `len` is zero, in which case there is no valid index and the caller must not
+    /// read from the array.
+    pub(crate) fn clamp_index(
+        &mut self,
+        index: AcirVar,
+        len: usize,
+        bits: u32,
+    ) -> Result<AcirVar, RuntimeError> {
+        let Some(last_index) = len.checked_sub(1) else {
+            return Ok(self.add_constant(F::zero()));
+        };
+        let last_index = self.add_constant(last_index);
+        self.min_var(index, last_index, AcirType::unsigned(bits))
+    }
+
+    /// Adds a new variable that is constrained to be `value` if `reset` is true and `max(acc, value)` otherwise.
+    ///
+    /// This allows running maxima to be restarted at segment boundaries. `reset` is assumed to be boolean.
+    pub(crate) fn running_max(
+        &mut self,
+        acc: AcirVar,
+        value: AcirVar,
+        reset: AcirVar,
+        typ: AcirType,
+    ) -> Result<AcirVar, RuntimeError> {
+        if let Some(reset_const) = self.var_to_expression(reset)?.to_const() {
+            return if reset_const.is_zero() { self.max_var(acc, value, typ) } else { Ok(value) };
+        }
+
+        let max = self.max_var(acc, value, typ)?;
+        self.select_var(reset, value, max)
+    }
+
+    /// Constrains `result` to be `when_true` if `cond` is true and `when_false` otherwise, when `predicate` is true.
+    ///
+    /// This verifies a `result` provided by a hint without computing the selection, by asserting that
+    /// `cond * (result - when_true) == 0` and `(1 - cond) * (result - when_false) == 0`.
+    /// `cond` is assumed to be boolean.
+    pub(crate) fn assert_conditional(
+        &mut self,
+        result: AcirVar,
+        cond: AcirVar,
+        when_true: AcirVar,
+        when_false: AcirVar,
+        predicate: AcirVar,
+    ) -> Result<(), RuntimeError> {
+        let zero = self.add_constant(F::zero());
+        let one = self.add_constant(F::one());
+        let not_cond = self.sub_var(one, cond)?;
+        for (selected, branch) in [(cond, when_true), (not_cond, when_false)] {
+            // A constant `cond` leaves a single equality, as the other one is multiplied by zero
+            if self.is_constant(&selected) && !self.is_constant_one(&selected) {
+                continue;
+            }
+            let diff = self.sub_var(result, branch)?;
+            let selected_diff = self.mul_var(selected, diff)?;
+            let predicated_diff = self.mul_var(selected_diff, predicate)?;
+            self.assert_eq_var(predicated_diff, zero, None)?;
+        }
+        Ok(())
+    }
+
+    /// Returns a variable which is constrained to be the number of leading zeros of the unsigned
+    /// `bit_size`-bit value `var`, which is `bit_size` when `var` is zero.
+    ///
+    /// The count `clz` is computed by a Brillig hint and then constrained as follows, where
+    /// `k = bit_size - clz` is the number of significant bits of `var`:
+    /// - `clz <= bit_size`,
+    /// - `var < 2^k`,
+    /// - `var >= 2^(k - 1)` if `var` is non-zero,
+    /// - `k == 0` if `var` is zero.
+    pub(crate) fn clz_var(&mut self, var: AcirVar, bit_size: u32) -> Result<AcirVar, RuntimeError> {
+        let zero = self.add_constant(F::zero());
+        let one = self.add_constant(F::one());
+
+        if let Some(constant) = self.var_to_expression(var)?.to_const() {
+            let leading_zeros = bit_size.saturating_sub(constant.num_bits());
+            return Ok(self.add_constant(leading_zeros));
+        }
+
+        // `2 * var` must not wrap around the field modulus
+        if bit_size + 2 >= F::max_num_bits() {
+            return Err(RuntimeError::InternalError(InternalError::General {
+                message: format!("cannot count the leading zeros of {bit_size}-bit values"),
+                call_stack: self.get_call_stack(),
+            }));
+        }
+
+        let bit_size_var = self.add_constant(bit_size);
+        let [clz_value]: [AcirValue; 1] = self
+            .stdlib_brillig_call(
+                one,
+                BrilligStdlibFunc::LeadingZeros,
+                &self.brillig_stdlib.get_code(BrilligStdlibFunc::LeadingZeros).clone(),
+                vec![
+                    AcirValue::Var(var, AcirType::unsigned(bit_size)),
+                    AcirValue::Var(bit_size_var, AcirType::field()),
+                ],
+                vec![AcirType::field()],
+                true,
+            )?
+            .try_into()
+            .expect("leading_zeros returns one value");
+        let clz_var = clz_value.into_var()?;
+
+        // Constrain `clz <= bit_size`, after range checking `clz` so that it fits in as many bits as `bit_size`.
+        let count_bits = u32::BITS - bit_size.leading_zeros();
+        self.range_constrain_var(
+            clz_var,
+            &NumericType::Unsigned { bit_size: count_bits },
+            None,
+            one,
+        )?;
+        self.bound_constraint_with_offset(clz_var, bit_size_var, zero, count_bits, one)?;
+
+        // Compute `2^k` from the bits of `k`, where `1 + bit * (2^(2^i) - 1)` is `2^(2^i)` if the
+        // `i`th bit is set and one otherwise
+        let significant_bits = self.sub_var(bit_size_var, clz_var)?;
+        let k_bits = self.bit_decompose(
+            Endian::Little,
+            significant_bits,
+            count_bits,
+            AcirType::unsigned(1),
+        )?;
+        let mut power = one;
+        for (i, (bit, _)) in k_bits.flatten().into_iter().enumerate() {
+            let multiplier = self.add_mul_var(one, power_of_two::<F>(1 << i) - F::one(), bit)?;
+            power = self.mul_var(power, multiplier)?;
+        }
+
+        // Constrain `var < 2^k`
+        self.bound_constraint_with_offset(var, power, one, bit_size + 1, one)?;
+
+        // Constrain `2^(k - 1) <= var`, i.e. `2^k <= 2 * var`, when `var` is non-zero
+        let var_is_zero = self.eq_var(var, zero)?;
+        let var_is_non_zero = self.sub_var(one, var_is_zero)?;
+        let double_var = self.add_var(var, var)?;
+        self.bound_constraint_with_offset(power, double_var, zero, bit_size + 1, var_is_non_zero)?;
+
+        // Constrain `k == 0` when `var` is zero
+        let zero_significant_bits = self.mul_var(var_is_zero, significant_bits)?;
+        self.assert_eq_var(zero_significant_bits, zero, None)?;
+
+        Ok(clz_var)
+    }
+
+    /// Returns a variable which is constrained to be the index of the least significant set bit of the
+    /// unsigned `bit_size`-bit value `var`, or `bit_size` if `var` is zero.
+    ///
+    /// The index is the number of trailing zero bits, which is counted by scanning the bits of `var`
+    /// from the least significant one while they are all zero.
+    pub(crate) fn find_first_set(
+        &mut self,
+        var: AcirVar,
+        bit_size: u32,
+    ) -> Result<AcirVar, RuntimeError> {
+        if let Some(constant) = self.var_to_expression(var)?.to_const() {
+            let trailing_zeros = BigUint::from_bytes_be(&constant.to_be_bytes())
+                .trailing_zeros()
+                .map_or(bit_size, |trailing_zeros| bit_size.min(trailing_zeros as u32));
+            return Ok(self.add_constant(trailing_zeros));
+        }
+
+        let bits = self.bit_decompose(Endian::Little, var, bit_size, AcirType::unsigned(1))?;
+        let one = self.add_constant(F::one());
+        let mut all_zero = one;
+        let mut index = self.add_constant(F::zero());
+        for (bit, _) in bits.flatten() {
+            let is_zero = self.sub_var(one, bit)?;
+            all_zero = self.mul_var(all_zero, is_zero)?;
+            index = self.add_var(index, all_zero)?;
+        }
+        Ok(index)
+    }
+
+    /// Returns a variable which is constrained to be the greatest common divisor of the unsigned
+    /// `bit_size`-bit values `lhs` and `rhs`, with `gcd(0, 0) == 0`.
+    ///
+    /// The GCD `g` and Bezout coefficients `x` and `y` are computed by a Brillig hint and then
+    /// constrained as follows (when `predicate` is true):
+    /// - `|x|, |y| <= 2^bit_size`, so that `lhs * x + rhs * y` cannot wrap around the field modulus,
+    /// - `lhs * x + rhs * y == g`, hence every common divisor of `lhs` and `rhs` divides `g`,
+    /// - `g` divides both `lhs` and `rhs`, hence `g` divides their GCD,
+    /// - `g == 0` only if `lhs == rhs == 0`, as otherwise the divisibility checks are vacuous.
+    ///
+    /// Together these imply that `g` is the GCD.
+    pub(crate) fn gcd(
+        &mut self,
+        lhs: AcirVar,
+        rhs: AcirVar,
+        bit_size: u32,
+        predicate: AcirVar,
+    ) -> Result<AcirVar, RuntimeError> {
+        let zero = self.add_constant(F::zero());
+        let one = self.add_constant(F::one());
+
+        let lhs_expr = self.var_to_expression(lhs)?;
+        let rhs_expr = self.var_to_expression(rhs)?;
+        let predicate_expr = self.var_to_expression(predicate)?;
+        match (lhs_expr.to_const(), rhs_expr.to_const(), predicate_expr.to_const()) {
+            (_, _, Some(predicate_const)) if predicate_const.is_zero() => return Ok(zero),
+            (Some(lhs_const), Some(rhs_const), _) => {
+                let lhs_const = BigUint::from_bytes_be(&lhs_const.to_be_bytes());
+                let rhs_const = BigUint::from_bytes_be(&rhs_const.to_be_bytes());
+                let gcd = F::from_be_bytes_reduce(&lhs_const.gcd(&rhs_const).to_bytes_be());
+                return Ok(self.add_constant(gcd));
+            }
+            _ => (),
+        }
+
+        // `lhs * x + rhs * y` is bounded by `2^(2 * bit_size + 1)` in absolute value.
+        if 2 * bit_size + 2 >= F::max_num_bits() {
+            return Err(RuntimeError::InternalError(InternalError::General {
+                message: format!("cannot compute the gcd of {bit_size}-bit values"),
+                call_stack: self.get_call_stack(),
+            }));
+        }
+
+        let [gcd_value, x_value, y_value]: [AcirValue; 3] = self
+            .stdlib_brillig_call(
+                predicate,
+                BrilligStdlibFunc::Gcd,
+                &self.brillig_stdlib.get_code(BrilligStdlibFunc::Gcd).clone(),
+                vec![
+                    AcirValue::Var(lhs, AcirType::unsigned(bit_size)),
+                    AcirValue::Var(rhs, AcirType::unsigned(bit_size)),
+                ],
+                vec![AcirType::unsigned(bit_size), AcirType::field(), AcirType::field()],
+                true,
+            )?
+            .try_into()
+            .expect("gcd returns three values");
+        let gcd_var = gcd_value.into_var()?;
+        let x_var = x_value.into_var()?;
+        let y_var = y_value.into_var()?;
+
+        // Constrain `-2^bit_size <= x, y <= 2^bit_size` by range checking `x + 2^bit_size` and `y + 2^bit_size`.
+        //
+        // We do not need to use a predicate in the range constraints because
+        // the coefficients are outputs of a brillig call.
+        let offset = self.add_constant(power_of_two::<F>(bit_size));
+        for coefficient in [x_var, y_var] {
+            let shifted_coefficient = self.add_var(coefficient, offset)?;
+            self.range_constrain_var(
+                shifted_coefficient,
+                &NumericType::Unsigned { bit_size: bit_size + 1 },
+                None,
+                one,
+            )?;
+        }
+
+        // predicate * (lhs * x + rhs * y - g) == 0
+        let lhs_x = self.mul_var(lhs, x_var)?;
+        let rhs_y = self.mul_var(rhs, y_var)?;
+        let bezout = self.add_var(lhs_x, rhs_y)?;
+        let bezout = self.sub_var(bezout, gcd_var)?;
+        let bezout = self.mul_var(bezout, predicate)?;
+        self.assert_eq_var(bezout, zero, None)?;
+
+        // predicate * (g == 0) * (lhs + rhs) == 0
+        //
+        // `lhs + rhs` cannot wrap around the field modulus so it is only zero if both values are zero.
+        let gcd_is_zero = self.eq_var(gcd_var, zero)?;
+        let sum = self.add_var(lhs, rhs)?;
+        let zero_gcd_sum = self.mul_var(gcd_is_zero, sum)?;
+        let zero_gcd_sum = self.mul_var(zero_gcd_sum, predicate)?;
+        self.assert_eq_var(zero_gcd_sum, zero, None)?;
+
+        // Check that `g` divides `lhs` and `rhs`. When `g` is zero we divide by one instead,
+        // which is fine as we know that `lhs` and `rhs` are then zero.
+        let divisor = self.add_var(gcd_var, gcd_is_zero)?;
+        for value in [lhs, rhs] {
+            let (_, remainder) =
+                self.euclidean_division_var(value, divisor, bit_size, predicate)?;
+            self.assert_eq_var(remainder, zero, None)?;
+        }
+
+        Ok(gcd_var)
+    }
+
+    /// Returns a variable which is constrained to be the greatest common divisor of the unsigned
+    /// `bit_size`-bit values `a` and `b`, with `gcd(0, 0) == 0`.
+    ///
+    /// As the Euclidean algorithm cannot be unrolled for unknown inputs, the GCD `g` and Bezout
+    /// cofactors `s` and `t` are computed by a Brillig hint and then constrained as follows:
+    /// - `|s|, |t| <= 2^bit_size`, so that `a * s + b * t` cannot wrap around the field modulus,
+    /// - `a * s + b * t == g`, hence every common divisor of `a` and `b` divides `g`,
+    /// - `g | a` and `g | b`, checked using [`AcirContext::euclidean_division_var`],
+    /// - `g == 0` only if `a == b == 0`, as otherwise the divisibility checks are vacuous.
+    ///
+    /// Unlike [`AcirContext::gcd`], these constraints always hold rather than being conditional on a predicate.
+    pub(crate) fn gcd_var(
+        &mut self,
+        a: AcirVar,
+        b: AcirVar,
+        bit_size: u32,
+    ) -> Result<AcirVar, RuntimeError> {
+        let zero = self.add_constant(F::zero());
+        let one = self.add_constant(F::one());
+
+        let a_expr = self.var_to_expression(a)?;
+        let b_expr = self.var_to_expression(b)?;
+        if let (Some(a_const), Some(b_const)) = (a_expr.to_const(), b_expr.to_const()) {
+            let a_const = BigUint::from_bytes_be(&a_const.to_be_bytes());
+            let b_const = BigUint::from_bytes_be(&b_const.to_be_bytes());
+            let gcd = F::from_be_bytes_reduce(&a_const.gcd(&b_const).to_bytes_be());
+            return Ok(self.add_constant(gcd));
+        }
+
+        // `a * s + b * t` is bounded by `2^(2 * bit_size + 1)` in absolute value.
+        if 2 * bit_size + 2 >= F::max_num_bits() {
+            return Err(RuntimeError::InternalError(InternalError::General {
+                message: format!("cannot compute the gcd of {bit_size}-bit values"),
+                call_stack: self.get_call_stack(),
+            }));
+        }
+
+        let [gcd_value, s_value, t_value]: [AcirValue; 3] = self
+            .stdlib_brillig_call(
+                one,
+                BrilligStdlibFunc::Gcd,
+                &self.brillig_stdlib.get_code(BrilligStdlibFunc::Gcd).clone(),
+                vec![
+                    AcirValue::Var(a, AcirType::unsigned(bit_size)),
+                    AcirValue::Var(b, AcirType::unsigned(bit_size)),
+                ],
+                vec![AcirType::unsigned(bit_size), AcirType::field(), AcirType::field()],
+                true,
+            )?
+            .try_into()
+            .expect("gcd returns three values");
+        let gcd_var = gcd_value.into_var()?;
+        let s_var = s_value.into_var()?;
+        let t_var = t_value.into_var()?;
+
+        // Constrain `-2^bit_size <= s, t <= 2^bit_size` by range checking `s + 2^bit_size` and `t + 2^bit_size`.
+        let offset = self.add_constant(power_of_two::<F>(bit_size));
+        for cofactor in [s_var, t_var] {
+            let shifted_cofactor = self.add_var(cofactor, offset)?;
+            self.range_constrain_var(
+                shifted_cofactor,
+                &NumericType::Unsigned { bit_size: bit_size + 1 },
+                None,
+                one,
+            )?;
+        }
+
+        // a * s + b * t == g
+        let a_s = self.mul_var(a, s_var)?;
+        let b_t = self.mul_var(b, t_var)?;
+        let bezout = self.add_var(a_s, b_t)?;
+        self.assert_eq_var(bezout, gcd_var, None)?;
+
+        // (g == 0) * (a + b) == 0
+        //
+        // `a + b` cannot wrap around the field modulus so it is only zero if both values are zero.
+        let gcd_is_zero = self.eq_var(gcd_var, zero)?;
+        let sum = self.add_var(a, b)?;
+        let zero_gcd_sum = self.mul_var(gcd_is_zero, sum)?;
+        self.assert_eq_var(zero_gcd_sum, zero, None)?;
+
+        // Check that `g` divides `a` and `b`. When `g` is zero we divide by one instead,
+        // which is fine as we know that `a` and `b` are then zero.
+        let divisor = self.add_var(gcd_var, gcd_is_zero)?;
+        for value in [a, b] {
+            let (_, remainder) = self.euclidean_division_var(value, divisor, bit_size, one)?;
+            self.assert_eq_var(remainder, zero, None)?;
+        }
+
+        Ok(gcd_var)
+    }
+
+    /// Returns the sum of the unsigned `bit_size`-bit values `lhs` and `rhs` wrapped to `bit_size` bits,
+    /// together with a boolean which is true if the addition overflowed.
+    pub(crate) fn overflowing_add(
+        &mut self,
+        lhs: AcirVar,
+        rhs: AcirVar,
+        bit_size: u32,
+    ) -> Result<(AcirVar, AcirVar), RuntimeError> {
+        if bit_size + 1 >= F::max_num_bits() {
+            return Err(RuntimeError::InternalError(InternalError::General {
+                message: format!("cannot compute the overflowing sum of {bit_size}-bit values"),
+                call_stack: self.get_call_stack(),
+            }));
+        }
+
+        // The full sum has at most `bit_size + 1` bits, the highest of which is the overflow bit.
+        let sum = self.add_var(lhs, rhs)?;
+        let divisor = self.add_constant(power_of_two::<F>(bit_size));
+        let one = self.add_constant(F::one());
+        let (overflow, wrapped) = self.euclidean_division_var(sum, divisor, bit_size + 1, one)?;
+        Ok((wrapped, overflow))
+    }
+
+    /// Linearly interpolates between the unsigned values `a` and `b`, returning `a + (((b - a) * t) >> scale_bits)`.
+    ///
+    /// `t` is a fixed-point fraction scaled by `2^scale_bits`, i.e. `t = 0` returns `a` and `t = 2^scale_bits`
+    /// returns `b`, and is assumed to lie in that range. `a` and `b` are assumed to be small enough that
+    /// `max(a, b) * 2^scale_bits` fits in `F::max_num_bits() - 4` bits. The shift rounds towards negative infinity
+    /// so that decreasing interpolations (`b < a`) behave as an arithmetic shift of the negative difference.
+    pub(crate) fn lerp(
+        &mut self,
+        a: AcirVar,
+        b: AcirVar,
+        t: AcirVar,
+        scale_bits: u32,
+    ) -> Result<AcirVar, RuntimeError> {
+        // The interpolated value is computed by dividing a value of at most `bit_size` bits by `2^scale_bits`.
+        // This leaves enough headroom for the division's quotient and remainder to be recombined without
+        // wrapping around the field modulus.
+        let bit_size = F::max_num_bits() - 4;
+        if scale_bits >= bit_size {
+            return Err(RuntimeError::InternalError(InternalError::General {
+                message: format!("cannot interpolate with a scale of {scale_bits} bits"),
+                call_stack: self.get_call_stack(),
+            }));
+        }
+
+        // `(b - a) * t` may be negative, so we instead shift the non-negative `a * (2^scale_bits - t) + b * t`,
+        // which is `a * 2^scale_bits + (b - a) * t`, and get the same result.
+        let scale = self.add_constant(power_of_two::<F>(scale_bits));
+        let one = self.add_constant(F::one());
+        let t_complement = self.sub_var(scale, t)?;
+        let a_weighted = self.mul_var(a, t_complement)?;
+        let b_weighted = self.mul_var(b, t)?;
+        let weighted_sum = self.add_var(a_weighted, b_weighted)?;
+        let (quotient, _) = self.euclidean_division_var(weighted_sum, scale, bit_size, one)?;
+        Ok(quotient)
+    }
+
+    /// Returns the sum of the unsigned `bit_size`-bit values `lhs` and `rhs`,
+    /// clamped to `2^bit_size - 1` if it does not fit in `bit_size` bits.
+    pub(crate) fn saturating_add(
+        &mut self,
+        lhs: AcirVar,
+        rhs: AcirVar,
+        bit_size: u32,
+    ) -> Result<AcirVar, RuntimeError> {
+        // The sum has up to `bit_size + 1` bits and is compared against `2^bit_size`,
+        // which requires one more bit of headroom.
+        if bit_size + 2 >= F::max_num_bits() {
+            return Err(RuntimeError::InternalError(InternalError::General {
+                message: format!("cannot compute the saturating sum of {bit_size}-bit values"),
+                call_stack: self.get_call_stack(),
+            }));
+        }
+
+        let lhs_expr = self.var_to_expression(lhs)?;
+        let rhs_expr = self.var_to_expression(rhs)?;
+        if let (Some(lhs_const), Some(rhs_const)) = (lhs_expr.to_const(), rhs_expr.to_const()) {
+            let lhs_const = BigUint::from_bytes_be(&lhs_const.to_be_bytes());
+            let rhs_const = BigUint::from_bytes_be(&rhs_const.to_be_bytes());
+            let max_const = (BigUint::from(1_u32) << bit_size) - 1_u32;
+            let sum = (lhs_const + rhs_const).min(max_const);
+            return Ok(self.add_constant(F::from_be_bytes_reduce(&sum.to_bytes_be())));
+        }
+
+        let sum = self.add_var(lhs, rhs)?;
+        let limit = self.add_constant(power_of_two::<F>(bit_size));
+        let overflows = self.more_than_eq_var(sum, limit, bit_size + 1)?;
+        let max = self.add_constant(power_of_two::<F>(bit_size) - F::one());
+        self.select_var(overflows, max, sum)
+    }
+
+    /// Returns the product of the unsigned `bit_size`-bit values `lhs` and `rhs`,
+    /// clamped to `2^bit_size - 1` if it does not fit in `bit_size` bits.
+    pub(crate) fn saturating_mul(
+        &mut self,
+        lhs: AcirVar,
+        rhs: AcirVar,
+        bit_size: u32,
+    ) -> Result<AcirVar, RuntimeError> {
+        // The full product has up to `2 * bit_size` bits and is compared against `2^bit_size`,
+        // which requires one more bit of headroom.
+        if 2 * bit_size + 1 >= F::max_num_bits() {
+            return Err(RuntimeError::InternalError(InternalError::General {
+                message: format!("cannot compute the saturating product of {bit_size}-bit values"),
+                call_stack: self.get_call_stack(),
+            }));
+        }
+
+        let lhs_expr = self.var_to_expression(lhs)?;
+        let rhs_expr = self.var_to_expression(rhs)?;
+        if let (Some(lhs_const), Some(rhs_const)) = (lhs_expr.to_const(), rhs_expr.to_const()) {
+            let lhs_const = BigUint::from_bytes_be(&lhs_const.to_be_bytes());
+            let rhs_const = BigUint::from_bytes_be(&rhs_const.to_be_bytes());
+            let max_const = (BigUint::from(1_u32) << bit_size) - 1_u32;
+            let product = (lhs_const * rhs_const).min(max_const);
+            return Ok(self.add_constant(F::from_be_bytes_reduce(&product.to_bytes_be())));
+        }
+
+        let product = self.mul_var(lhs, rhs)?;
+        let limit = self.add_constant(power_of_two::<F>(bit_size));
+        let overflows = self.more_than_eq_var(product, limit, 2 * bit_size)?;
+        let max = self.add_constant(power_of_two::<F>(bit_size) - F::one());
+        self.select_var(overflows, max, product)
+    }
+
+    /// Returns `|a - b|` for the unsigned `bits`-bit values `a` and `b`.
+    ///
+    /// Both differences are computed and the non-negative one is selected, so the result never underflows.
+    pub(crate) fn abs_diff(
+        &mut self,
+        a: AcirVar,
+        b: AcirVar,
+        bits: u32,
+    ) -> Result<AcirVar, RuntimeError> {
+        let a_expr = self.var_to_expression(a)?;
+        let b_expr = self.var_to_expression(b)?;
+        if let (Some(a_const), Some(b_const)) = (a_expr.to_const(), b_expr.to_const()) {
+            let a_const = BigUint::from_bytes_be(&a_const.to_be_bytes());
+            let b_const = BigUint::from_bytes_be(&b_const.to_be_bytes());
+            let diff = if a_const < b_const { b_const - a_const } else { a_const - b_const };
+            return Ok(self.add_constant(F::from_be_bytes_reduce(&diff.to_bytes_be())));
+        }
+
+        let a_is_less = self.less_than_var(a, b, bits)?;
+        let b_minus_a = self.sub_var(b, a)?;
+        let a_minus_b = self.sub_var(a, b)?;
+        self.select_var(a_is_less, b_minus_a, a_minus_b)
+    }
+
+    /// Returns the booleans `(a < b, a == b, a > b)` for the unsigned `bits`-bit values `a` and `b`.
+    ///
+    /// Exactly one of the booleans is true, as `a == b` is derived from the two strict comparisons.
+    pub(crate) fn compare_three_way(
+        &mut self,
+        a: AcirVar,
+        b: AcirVar,
+        bits: u32,
+    ) -> Result<(AcirVar, AcirVar, AcirVar), RuntimeError> {
+        let a_expr = self.var_to_expression(a)?;
+        let b_expr = self.var_to_expression(b)?;
+        if let (Some(a_const), Some(b_const)) = (a_expr.to_const(), b_expr.to_const()) {
+            let a_const = BigUint::from_bytes_be(&a_const.to_be_bytes());
+            let b_const = BigUint::from_bytes_be(&b_const.to_be_bytes());
+            let is_less = self.add_constant(a_const < b_const);
+            let is_equal = self.add_constant(a_const == b_const);
+            let is_greater = self.add_constant(a_const > b_const);
+            return Ok((is_less, is_equal, is_greater));
+        }
+
+        let is_less = self.less_than_var(a, b, bits)?;
+        let is_greater = self.less_than_var(b, a, bits)?;
+        let one = self.add_constant(F::one());
+        let not_less = self.sub_var(one, is_less)?;
+        let is_equal = self.sub_var(not_less, is_greater)?;
+        Ok((is_less, is_equal, is_greater))
+    }
+
+    /// Returns the `width`-bit CRC register `state` after feeding in `byte`, most significant bit first,
+    /// for the generator `polynomial` given without its leading term.
+    ///
+    /// The byte is XORed into the high bits of the register, which is then shifted left once per bit,
+    /// XORing in the polynomial whenever the bit shifted out is set.
+    pub(crate) fn crc_update(
+        &mut self,
+        state: AcirVar,
+        byte: AcirVar,
+        polynomial: u128,
+        width: u32,
+    ) -> Result<AcirVar, RuntimeError> {
+        if !(8..128).contains(&width) || polynomial >> width != 0 {
+            return Err(RuntimeError::InternalError(InternalError::General {
+                message: format!("invalid {width}-bit CRC polynomial {polynomial:#x}"),
+                call_stack: self.get_call_stack(),
+            }));
+        }
+
+        let state_expr = self.var_to_expression(state)?;
+        let byte_expr = self.var_to_expression(byte)?;
+        if let (Some(state_const), Some(byte_const)) = (state_expr.to_const(), byte_expr.to_const())
+        {
+            let state_const = state_const.try_into_u128().filter(|state| state >> width == 0);
+            let byte_const = byte_const.try_into_u128().filter(|byte| *byte <= 0xff);
+            if let (Some(state_const), Some(byte_const)) = (state_const, byte_const) {
+                let result = crc_update_constant(state_const, byte_const, polynomial, width);
+                return Ok(self.add_constant(result));
+            }
+        }
+
+        let typ = AcirType::unsigned(width);
+        let byte_shift = self.add_constant(power_of_two::<F>(width - 8));
+        let shifted_byte = self.mul_var(byte, byte_shift)?;
+        let mut state = self.xor_var(state, shifted_byte, typ.clone())?;
+
+        let one = self.add_constant(F::one());
+        let two = self.add_constant(F::from(2_u128));
+        let register_limit = self.add_constant(power_of_two::<F>(width));
+        let polynomial = self.add_constant(polynomial);
+        for _ in 0..8 {
+            let doubled = self.mul_var(state, two)?;
+            let (high_bit, shifted) =
+                self.euclidean_division_var(doubled, register_limit, width + 1, one)?;
+            let feedback = self.mul_var(high_bit, polynomial)?;
+            state = self.xor_var(shifted, feedback, typ.clone())?;
+        }
+        Ok(state)
+    }
+
+    /// Asserts that `var` is a canonical field element, which always holds as every field element is
+    /// reduced modulo the field's prime. No opcodes are emitted.
+    ///
+    /// This mirrors how [`AcirContext::range_constrain_var`] handles `NumericType::NativeField`, so that
+    /// callers can assert canonicity alongside integer range constraints without special-casing fields.
+    pub(crate) fn assert_field_canonical(&mut self, _var: AcirVar) -> Result<(), RuntimeError> {
+        Ok(())
+    }
+
+    /// Returns `var` truncated to `bits` bits if `flag` is true and `var` otherwise,
+    /// where `var` has at most `max_bits` bits.
+    ///
+    /// A constant `flag` only emits the constraints of the branch it selects.
+    pub(crate) fn conditional_truncate(
+        &mut self,
+        var: AcirVar,
+        bits: u32,
+        max_bits: u32,
+        flag: AcirVar,
+    ) -> Result<AcirVar, RuntimeError> {
+        if let Some(flag_const) = self.var_to_expression(flag)?.to_const() {
+            return if flag_const.is_zero() {
+                Ok(var)
+            } else {
+                self.truncate_var(var, bits, max_bits)
+            };
+        }
+
+        let truncated = self.truncate_var(var, bits, max_bits)?;
+        self.select_var(flag, truncated, var)
+    }
+
+    /// Returns `(idx + 1) % capacity` if `predicate` is true and `idx` otherwise, for a `bits`-bit index `idx`.
+    ///
+    /// For a power-of-two `capacity` the incremented index is truncated. Otherwise `idx` is assumed to
+    /// be less than `capacity`, so that the incremented index only needs to be reset to zero when it
+    /// reaches `capacity`.
+    pub(crate) fn wrapping_increment(
+        &mut self,
+        idx: AcirVar,
+        capacity: u128,
+        bits: u32,
+        predicate: AcirVar,
+    ) -> Result<AcirVar, RuntimeError> {
+        if capacity == 0 {
+            return Err(RuntimeError::InternalError(InternalError::General {
+                message: "cannot wrap an index at a capacity of zero".to_string(),
+                call_stack: self.get_call_stack(),
+            }));
+        }
+
+        let incremented = self.add_var(idx, predicate)?;
+        if capacity.is_power_of_two() {
+            return self.truncate_var(incremented, capacity.trailing_zeros(), bits + 1);
+        }
+
+        let zero = self.add_constant(F::zero());
+        let capacity = self.add_constant(capacity);
+        let wraps = self.eq_var(incremented, capacity)?;
+        self.select_var(wraps, zero, incremented)
+    }
+
+    /// Returns `lhs >> rhs` for the `bit_size`-bit values `lhs` and `rhs`, which is zero when `rhs >= bit_size`.
+    ///
+    /// A constant `rhs` is folded into a truncation. Otherwise `2^rhs` is computed from the bits of `rhs`,
+    /// clamped below `bit_size`, and `lhs` is divided by it under `predicate`.
+    pub(crate) fn shift_right_var(
+        &mut self,
+        lhs: AcirVar,
+        rhs: AcirVar,
+        bit_size: u32,
+        predicate: AcirVar,
+    ) -> Result<AcirVar, RuntimeError> {
+        let zero = self.add_constant(F::zero());
+        let one = self.add_constant(F::one());
+
+        if let Some(rhs_const) = self.var_to_expression(rhs)?.to_const() {
+            let shift = rhs_const.try_to_u32().filter(|shift| *shift < bit_size);
+            return match shift {
+                None => Ok(zero),
+                Some(0) => Ok(lhs),
+                Some(shift) => {
+                    // `lhs >> shift == (lhs - (lhs mod 2^shift)) / 2^shift`
+                    let remainder = self.truncate_var(lhs, shift, bit_size)?;
+                    let shifted_out = self.sub_var(lhs, remainder)?;
+                    let inverse = self.add_constant(power_of_two::<F>(shift).inverse());
+                    self.mul_var(shifted_out, inverse)
+                }
+            };
+        }
+
+        // Shifting by `bit_size` or more gives zero, so the shift is clamped to zero in that case
+        // and the quotient discarded.
+        let bit_size_var = self.add_constant(bit_size);
+        let overflows = self.more_than_eq_var(rhs, bit_size_var, bit_size)?;
+        let shift = self.select_var(overflows, zero, rhs)?;
+
+        // `1 + bit * (2^(2^i) - 1)` is `2^(2^i)` if the bit is set and one otherwise
+        let shift_bit_size = (u32::BITS - (bit_size - 1).leading_zeros()).max(1);
+        let shift_bits =
+            self.bit_decompose(Endian::Little, shift, shift_bit_size, AcirType::unsigned(1))?;
+        let mut divisor = one;
+        for (i, (bit, _)) in shift_bits.flatten().into_iter().enumerate() {
+            let multiplier = self.add_mul_var(one, power_of_two::<F>(1 << i) - F::one(), bit)?;
+            divisor = self.mul_var(divisor, multiplier)?;
+        }
+
+        let (quotient, _) = self.euclidean_division_var(lhs, divisor, bit_size, predicate)?;
+        let in_range = self.sub_var(one, overflows)?;
+        self.mul_var(quotient, in_range)
+    }
+
+    /// Returns `(var << shift) mod 2^bit_size` for the `bit_size`-bit value `var`, where `shift` is constrained
+    /// to be at most `max_shift`.
+    ///
+    /// A symbolic `shift` is decomposed into bits and `var` is multiplied by `2^(2^i)` for each set bit `i`,
+    /// requiring one multiplication per bit of `max_shift`.
+    pub(crate) fn dynamic_shift_left(
+        &mut self,
+        var: AcirVar,
+        shift: AcirVar,
+        max_shift: u32,
+        bit_size: u32,
+    ) -> Result<AcirVar, RuntimeError> {
+        // The shifted value has at most `bit_size + max_shift` bits before it is truncated.
+        // The truncation is done on one more bit so that it can divide by `2^bit_size` even when nothing was shifted.
+        let max_bit_size = bit_size + max_shift + 1;
+        if max_bit_size >= F::max_num_bits() {
+            return Err(RuntimeError::InternalError(InternalError::General {
+                message: format!("cannot shift a {bit_size}-bit value by up to {max_shift} bits"),
+                call_stack: self.get_call_stack(),
+            }));
+        }
+
+        if let Some(shift_const) = self.var_to_expression(shift)?.to_const() {
+            let shift_const = shift_const.try_to_u32().filter(|shift| *shift <= max_shift);
+            let Some(shift_const) = shift_const else {
+                return Err(RuntimeError::InternalError(InternalError::General {
+                    message: format!("shift is larger than the maximum shift of {max_shift}"),
+                    call_stack: self.get_call_stack(),
+                }));
+            };
+            let multiplier = self.add_constant(power_of_two::<F>(shift_const));
+            let shifted = self.mul_var(var, multiplier)?;
+            return self.truncate_var(shifted, bit_size, bit_size + shift_const + 1);
+        }
+
+        let shift_bit_size = (u32::BITS - max_shift.leading_zeros()).max(1);
+        let max_shift_var = self.add_constant(max_shift);
+        let in_range = self.more_than_eq_var(max_shift_var, shift, shift_bit_size)?;
+        let one = self.add_constant(F::one());
+        self.assert_eq_var(in_range, one, None)?;
+
+        let shift_bits =
+            self.bit_decompose(Endian::Little, shift, shift_bit_size, AcirType::unsigned(1))?;
+        let mut shifted = var;
+        for (i, (bit, _)) in shift_bits.flatten().into_iter().enumerate() {
+            // `1 + bit * (2^(2^i) - 1)` is `2^(2^i)` if the bit is set and one otherwise
+            let multiplier = self.add_mul_var(one, power_of_two::<F>(1 << i) - F::one(), bit)?;
+            shifted = self.mul_var(shifted, multiplier)?;
+        }
+        self.truncate_var(shifted, bit_size, max_bit_size)
+    }
+
+    /// Returns an 'AcirVar' containing the boolean value lhs>=rhs, assuming lhs and rhs are signed integers of size bit_count.
+    /// This is the negation of [`AcirContext::less_than_signed`]: with the same difference diff = lhs-rhs+2^n,
+    /// if same sign, lhs>=rhs <=> diff>=2^n, and the result is flipped if the signs differ.
+    /// Equal operands give diff = 2^n and so return 1.
+    pub(crate) fn more_than_eq_signed(
+        &mut self,
+        lhs: AcirVar,
+        rhs: AcirVar,
+        bit_count: u32,
+    ) -> Result<AcirVar, RuntimeError> {
+        let pow = self.add_constant(F::from(1_u128 << (bit_count)));
+        let same_sign = self.sign_bits_xor(lhs, rhs, bit_count)?;
+
+        let no_underflow = self.add_var(lhs, pow)?;
+        let diff = self.sub_var(no_underflow, rhs)?;
+        let diff_sign = self.more_than_eq_var(diff, pow, bit_count + 1)?;
+
+        self.xor_var(
+            diff_sign,
+            same_sign,
+            AcirType::NumericType(NumericType::Signed { bit_size: 1 }),
+        )
+    }
+
+    /// Returns an array of booleans, where the `i`th boolean is true if the `bits`-bit value `var`
+    /// lies within the half-open range `ranges[i]`, i.e. `lo <= var < hi`.
+    ///
+    /// Ranges may overlap, in which case several booleans can be true.
+    ///
+    /// The bounds must fit in `bits` bits, except for `hi` which may be `2^bits` to include
+    /// every value from `lo` upwards.
+    pub(crate) fn range_membership(
+        &mut self,
+        var: AcirVar,
+        ranges: &[(F, F)],
+        bits: u32,
+    ) -> Result<AcirValue, RuntimeError> {
+        let membership = try_vecmap(ranges, |(lo, hi)| {
+            let hi_is_unbounded = hi.num_bits() == bits + 1 && *hi == power_of_two(bits);
+            if lo.num_bits() > bits || (hi.num_bits() > bits && !hi_is_unbounded) {
+                return Err(RuntimeError::InternalError(InternalError::General {
+                    message: format!("range [{lo}, {hi}) does not fit in {bits} bits"),
+                    call_stack: self.get_call_stack(),
+                }));
+            }
+
+            let lo = self.add_constant(*lo);
+            let mut is_member = self.more_than_eq_var(var, lo, bits)?;
+            if !hi_is_unbounded {
+                let hi = self.add_constant(*hi);
+                let below_hi = self.less_than_var(var, hi, bits)?;
+                is_member = self.mul_var(is_member, below_hi)?;
+            }
+            Ok(AcirValue::Var(is_member, AcirType::unsigned(1)))
+        })?;
+        Ok(AcirValue::Array(membership.into()))
+    }
+
+    /// Returns `AcirVar`s constrained to be the byte decomposition of the provided input
+    pub(crate) fn byte_decompose(
+        &mut self,
+        endian: Endian,
+        input_var: AcirVar,
+        byte_count: u32,
+        result_element_type: AcirType,
+    ) -> Result<AcirValue, RuntimeError> {
+        let radix_var = self.add_constant(256_u128);
+        self.radix_decompose(endian, input_var, radix_var, byte_count, result_element_type)
+    }
+
+    /// Returns `byte_count` variables constrained to be the little-endian byte decomposition of `var`.
+    pub(crate) fn field_to_bytes(
+        &mut self,
+        var: AcirVar,
+        byte_count: u32,
+    ) -> Result<Vec<AcirVar>, RuntimeError> {
+        let bytes = self.byte_decompose(Endian::Little, var, byte_count, AcirType::unsigned(8))?;
+        Ok(vecmap(bytes.flatten(), |(byte, _)| byte))
+    }
+
+    /// Returns a variable holding the value of the little-endian `bytes`.
+    pub(crate) fn bytes_to_field(&mut self, bytes: &[AcirVar]) -> Result<AcirVar, RuntimeError> {
+        let mut result = self.add_constant(F::zero());
+        for (i, byte) in bytes.iter().enumerate() {
+            result = self.add_mul_var(result, power_of_two(8 * i as u32), *byte)?;
+        }
+        Ok(result)
+    }
+
+    /// Returns the unsigned `bit_size`-bit value `var` with the order of its bits reversed.
+    pub(crate) fn bit_reverse(
+        &mut self,
+        var: AcirVar,
+        bit_size: u32,
+    ) -> Result<AcirVar, RuntimeError> {
+        if let Some(constant) = self.var_to_expression(var)?.to_const() {
+            if let Some(constant) = constant
+                .try_into_u128()
+                .filter(|_| bit_size <= 128 && constant.num_bits() <= bit_size)
+            {
+                let reversed = constant.reverse_bits().checked_shr(128 - bit_size).unwrap_or(0);
+                return Ok(self.add_constant(reversed));
+            }
+        }
+
+        let bits = self.bit_decompose(Endian::Little, var, bit_size, AcirType::unsigned(1))?;
+        let mut result = self.add_constant(F::zero());
+        for (i, (bit, _)) in bits.flatten().into_iter().enumerate() {
+            result = self.add_mul_var(result, power_of_two(bit_size - 1 - i as u32), bit)?;
+        }
+        Ok(result)
+    }
+
+    /// Returns the unsigned `bit_size`-bit value `var` with its bits rotated left by `rotation`, modulo `bit_size`.
+    ///
+    /// The low `bit_size - rotation` bits are split off by a truncation, after which the rotation
+    /// only requires linear arithmetic.
+    pub(crate) fn rotate_left_var(
+        &mut self,
+        var: AcirVar,
+        rotation: u32,
+        bit_size: u32,
+    ) -> Result<AcirVar, RuntimeError> {
+        let rotation = if bit_size == 0 { 0 } else { rotation % bit_size };
+        if rotation == 0 {
+            return Ok(var);
+        }
+
+        if let Some(constant) = self.var_to_expression(var)?.to_const() {
+            if let Some(constant) = constant
+                .try_into_u128()
+                .filter(|_| bit_size <= 128 && constant.num_bits() <= bit_size)
+            {
+                let mask = u128::MAX >> (128 - bit_size);
+                let rotated = ((constant << rotation) | (constant >> (bit_size - rotation))) & mask;
+                return Ok(self.add_constant(rotated));
+            }
+        }
+
+        // `var = high * 2^(bit_size - rotation) + low`
+        let low_bits = bit_size - rotation;
+        let low = self.truncate_var(var, low_bits, bit_size)?;
+        let high_shifted = self.sub_var(var, low)?;
+        let low_bits_inverse = self.add_constant(power_of_two::<F>(low_bits).inverse());
+        let high = self.mul_var(high_shifted, low_bits_inverse)?;
+
+        // `low * 2^rotation + high`
+        self.add_mul_var(high, power_of_two(rotation), low)
+    }
+
+    /// Returns the unsigned `bit_size`-bit value `var` with its bits rotated right by `rotation`, modulo `bit_size`.
+    pub(crate) fn rotate_right_var(
+        &mut self,
+        var: AcirVar,
+        rotation: u32,
+        bit_size: u32,
+    ) -> Result<AcirVar, RuntimeError> {
+        if bit_size == 0 {
+            return Ok(var);
+        }
+        self.rotate_left_var(var, bit_size - rotation % bit_size, bit_size)
+    }
+
+    /// Returns `x` with the bits selected by `mask` set if the boolean `set` is true, or cleared otherwise.
+    ///
+    /// This computes `(x & !mask) | (broadcast(set) & mask)`, where `broadcast(set)` has every bit
+    /// equal to `set`. Constant operands are folded by the underlying bitwise operations.
+    pub(crate) fn conditional_set_bits(
+        &mut self,
+        x: AcirVar,
+        mask: AcirVar,
+        set: AcirVar,
+        typ: AcirType,
+    ) -> Result<AcirVar, RuntimeError> {
+        let inverted_mask = self.not_var(mask, typ.clone())?;
+        let kept_bits = self.and_var(x, inverted_mask, typ.clone())?;
+
+        let all_ones = power_of_two::<F>(typ.bit_size::<F>()) - F::one();
+        let all_ones = self.add_constant(all_ones);
+        let broadcast = self.mul_var(set, all_ones)?;
+        let new_bits = self.and_var(broadcast, mask, typ.clone())?;
+
+        self.or_var(kept_bits, new_bits, typ)
+    }
+
+    /// Returns a variable holding `var` with the order of its `byte_count` least significant bytes reversed.
+    ///
+    /// `var` is constrained to fit in `byte_count` bytes.
+    pub(crate) fn byte_reverse(
+        &mut self,
+        var: AcirVar,
+        byte_count: u32,
+    ) -> Result<AcirVar, RuntimeError> {
+        if let Some(constant) = self.var_to_expression(var)?.to_const() {
+            let mut le_bytes = constant.to_be_bytes();
+            le_bytes.reverse();
+            let byte_count = byte_count as usize;
+            if byte_count <= le_bytes.len() && le_bytes[byte_count..].iter().all(|byte| *byte == 0)
+            {
+                // The big-endian bytes of the result are the little-endian bytes of `var`.
+                let reversed = F::from_be_bytes_reduce(&le_bytes[..byte_count]);
+                return Ok(self.add_constant(reversed));
+            }
+        }
+
+        let mut bytes = self.field_to_bytes(var, byte_count)?;
+        bytes.reverse();
+        self.bytes_to_field(&bytes)
+    }
+
+    /// Returns a variable holding the Morton encoding of `x` and `y`, which interleaves their
+    /// `bits_each` least significant bits starting with the least significant bit of `x`.
+    ///
+    /// `x` and `y` are constrained to fit in `bits_each` bits.
+    pub(crate) fn morton_interleave(
+        &mut self,
+        x: AcirVar,
+        y: AcirVar,
+        bits_each: u32,
+    ) -> Result<AcirVar, RuntimeError> {
+        if 2 * bits_each >= F::max_num_bits() {
+            return Err(RuntimeError::InternalError(InternalError::General {
+                message: format!("cannot interleave two {bits_each}-bit values into a field"),
+                call_stack: self.get_call_stack(),
+            }));
+        }
+
+        let x_expr = self.var_to_expression(x)?;
+        let y_expr = self.var_to_expression(y)?;
+        if let (Some(x_const), Some(y_const)) = (x_expr.to_const(), y_expr.to_const()) {
+            if x_const.num_bits() <= bits_each && y_const.num_bits() <= bits_each {
+                let x_const = BigUint::from_bytes_be(&x_const.to_be_bytes());
+                let y_const = BigUint::from_bytes_be(&y_const.to_be_bytes());
+                let mut interleaved = BigUint::from(0_u32);
+                for i in 0..u64::from(bits_each) {
+                    interleaved.set_bit(2 * i, x_const.bit(i));
+                    interleaved.set_bit(2 * i + 1, y_const.bit(i));
+                }
+                return Ok(self.add_constant(F::from_be_bytes_reduce(&interleaved.to_bytes_be())));
+            }
+        }
+
+        let x_bits = self.bit_decompose(Endian::Little, x, bits_each, AcirType::unsigned(1))?;
+        let y_bits = self.bit_decompose(Endian::Little, y, bits_each, AcirType::unsigned(1))?;
+        let mut result = self.add_constant(F::zero());
+        for (i, ((x_bit, _), (y_bit, _))) in
+            x_bits.flatten().into_iter().zip(y_bits.flatten()).enumerate()
+        {
+            let i = i as u32;
+            result = self.add_mul_var(result, power_of_two(2 * i), x_bit)?;
+            result = self.add_mul_var(result, power_of_two(2 * i + 1), y_bit)?;
+        }
+        Ok(result)
+    }
+
+    /// Returns the number of set bits in the `bit_size`-bit value `var`.
+    ///
+    /// This constrains `var` to fit in `bit_size` bits.
+    pub(crate) fn popcount(
+        &mut self,
+        var: AcirVar,
+        bit_size: u32,
+    ) -> Result<AcirVar, RuntimeError> {
+        if let Some(constant) = self.var_to_expression(var)?.to_const() {
+            if constant.num_bits() <= bit_size {
+                let constant = BigUint::from_bytes_be(&constant.to_be_bytes());
+                return Ok(self.add_constant(F::from(u128::from(constant.count_ones()))));
+            }
+        }
+
+        let bits = self.bit_decompose(Endian::Little, var, bit_size, AcirType::unsigned(1))?;
+        let mut count = self.add_constant(F::zero());
+        for (bit, _) in bits.flatten() {
+            count = self.add_var(count, bit)?;
+        }
+        Ok(count)
+    }
+
+    /// Returns a boolean which is true if the `bit_size`-bit value `lhs` has more set bits than `rhs`.
+    pub(crate) fn popcount_greater(
+        &mut self,
+        lhs: AcirVar,
+        rhs: AcirVar,
+        bit_size: u32,
+    ) -> Result<AcirVar, RuntimeError> {
+        let lhs_count = self.popcount(lhs, bit_size)?;
+        let rhs_count = self.popcount(rhs, bit_size)?;
+        // Both counts are at most `bit_size`
+        let count_bit_size = u32::BITS - bit_size.leading_zeros();
+        self.less_than_var(rhs_count, lhs_count, count_bit_size)
+    }
+
+    /// Returns the index of the first of the ascending constant `thresholds` which the `bits`-bit value `var`
+    /// is less than, or `thresholds.len()` if `var` is not less than any of them.
+    ///
+    /// As the thresholds are ascending, this is the number of thresholds which `var` is greater than or equal to.
+    pub(crate) fn bucket_index(
+        &mut self,
+        var: AcirVar,
+        thresholds: &[F],
+        bits: u32,
+    ) -> Result<AcirVar, RuntimeError> {
+        if thresholds.windows(2).any(|pair| pair[0] > pair[1]) {
+            return Err(RuntimeError::InternalError(InternalError::General {
+                message: "bucket thresholds must be in ascending order".to_string(),
+                call_stack: self.get_call_stack(),
+            }));
+        }
+        if let Some(threshold) = thresholds.iter().find(|threshold| threshold.num_bits() > bits) {
+            return Err(RuntimeError::InternalError(InternalError::General {
+                message: format!("bucket threshold {threshold} does not fit in {bits} bits"),
+                call_stack: self.get_call_stack(),
+            }));
+        }
+
+        let mut index = self.add_constant(F::zero());
+        for threshold in thresholds {
+            let threshold = self.add_constant(*threshold);
+            let is_above = self.more_than_eq_var(var, threshold, bits)?;
+            index = self.add_var(index, is_above)?;
+        }
+        Ok(index)
+    }
+
+    /// Evaluates the polynomial `coeffs[0] + coeffs[1] * x + ... + coeffs[n] * x^n` at `x` using Horner's scheme,
+    /// which requires one multiplication per coefficient after the first.
+    pub(crate) fn eval_poly(&mut self, coeffs: &[F], x: AcirVar) -> Result<AcirVar, RuntimeError> {
+        let Some((leading_coeff, coeffs)) = coeffs.split_last() else {
+            return Ok(self.add_constant(F::zero()));
+        };
+
+        // `acc = acc * x + c_i`, starting from the leading coefficient
+        let mut acc = self.add_constant(*leading_coeff);
+        for coeff in coeffs.iter().rev() {
+            let coeff = self.add_constant(*coeff);
+            acc = self.mul_var(acc, x)?;
+            acc = self.add_var(acc, coeff)?;
+        }
+        Ok(acc)
+    }
+
+    /// Constrains the value located at `index` in the memory `block_id` to become `value` if `predicate`
+    /// is true, and to keep its current value otherwise.
+    ///
+    /// `predicate` is assumed to be boolean.
+    pub(crate) fn conditional_write_to_memory(
+        &mut self,
+        block_id: BlockId,
+        index: &AcirVar,
+        value: &AcirVar,
+        predicate: AcirVar,
+    ) -> Result<(), RuntimeError> {
+        if self.is_constant_one(&predicate) {
+            self.write_to_memory(block_id, index, value)?;
+            return Ok(());
+        }
+        if self.is_constant(&predicate) {
+            // The predicate is false so the memory is left untouched.
+            return Ok(());
+        }
+
+        // `current + predicate * (value - current)`
+        let current = self.read_from_memory(block_id, index)?;
+        let diff = self.sub_var(*value, current)?;
+        let predicated_diff = self.mul_var(diff, predicate)?;
+        let new_value = self.add_var(current, predicated_diff)?;
+        self.write_to_memory(block_id, index, &new_value)?;
+        Ok(())
+    }
+
+    /// Returns `(b, a)` if `cond` is true and `(a, b)` otherwise.
+    ///
+    /// Both results share a single multiplication, `cond * (b - a)`. `cond` is assumed to be boolean.
+    pub(crate) fn conditional_swap(
+        &mut self,
+        cond: AcirVar,
+        a: AcirVar,
+        b: AcirVar,
+    ) -> Result<(AcirVar, AcirVar), RuntimeError> {
+        let diff = self.sub_var(b, a)?;
+        let selected_diff = self.mul_var(cond, diff)?;
+        let first = self.add_var(a, selected_diff)?;
+        let second = self.sub_var(b, selected_diff)?;
+        Ok((first, second))
+    }
+
+    /// Orders the `bits`-bit unsigned values at `i` and `j` in the memory `block_id`, swapping them
+    /// if the value at `i` is greater than the value at `j`.
+    pub(crate) fn compare_exchange(
+        &mut self,
+        block_id: BlockId,
+        i: &AcirVar,
+        j: &AcirVar,
+        bits: u32,
+    ) -> Result<(), RuntimeError> {
+        let value_i = self.read_from_memory(block_id, i)?;
+        let value_j = self.read_from_memory(block_id, j)?;
+        let i_is_greater = self.less_than_var(value_j, value_i, bits)?;
+        let (new_i, new_j) = self.conditional_swap(i_is_greater, value_i, value_j)?;
+        self.write_to_memory(block_id, i, &new_i)?;
+        self.write_to_memory(block_id, j, &new_j)?;
+        Ok(())
+    }
+
+    /// Adds `delta` to the unsigned `bit_size`-bit counter at `index` in the memory `block_id`,
+    /// saturating at `2^bit_size - 1` rather than overflowing.
+    pub(crate) fn saturating_accumulate(
+        &mut self,
+        block_id: BlockId,
+        index: &AcirVar,
+        delta: AcirVar,
+        bit_size: u32,
+    ) -> Result<(), RuntimeError> {
+        let counter = self.read_from_memory(block_id, index)?;
+        let new_counter = self.saturating_add(counter, delta, bit_size)?;
+        self.write_to_memory(block_id, index, &new_counter)?;
+        Ok(())
+    }
+
+    /// Constrains the first `len` slots of memory `block_id` to be cleared to zero if `cond` is true,
+    /// and to keep their current values otherwise.
+    ///
+    /// `cond` is assumed to be boolean.
+    pub(crate) fn conditional_clear_memory(
+        &mut self,
+        block_id: BlockId,
+        len: usize,
+        cond: AcirVar,
+    ) -> Result<(), RuntimeError> {
+        let zero = self.add_constant(F::zero());
+        for i in 0..len {
+            let index = self.add_constant(i);
+            self.conditional_write_to_memory(block_id, &index, &zero, cond)?;
+        }
+        Ok(())
+    }
+
+    /// Initializes an array in memory of length `total_len` where the first slots hold the flattened `values`
+    /// and all remaining slots hold `default`.
+    ///
+    /// Returns an error if the flattened `values` do not fit into `total_len` slots.
+    pub(crate) fn initialize_array_prefix(
+        &mut self,
+        block_id: BlockId,
+        values: &[AcirValue],
+        total_len: usize,
+        default: AcirVar,
+        databus: BlockType,
+    ) -> Result<(), InternalError> {
+        let mut initialized_values = Vec::with_capacity(total_len);
+        for value in values {
+            self.initialize_array_inner(&mut initialized_values, value.clone())?;
+        }
+
+        if initialized_values.len() > total_len {
+            return Err(InternalError::General {
+                message: format!(
+                    "cannot initialize {} values into an array of length {total_len}",
+                    initialized_values.len()
+                ),
+                call_stack: self.get_call_stack(),
+            });
+        }
+
+        let default_witness = self.var_to_witness(default)?;
+        initialized_values.resize(total_len, default_witness);
+
+        self.acir_ir.push_opcode(Opcode::MemoryInit {
+            block_id,
+            init: initialized_values,
+            block_type: databus,
+        });
+
+        Ok(())
+    }
+
+    /// Writes `value` at the (possibly symbolic) `index` of the first `len` slots of memory `block_id`
+    /// by scanning over every slot and only updating the one whose position matches `index`.
+    ///
+    /// This emulates a dynamic write without relying on the memory opcode resolving `index`.
+    /// `index` is constrained to be less than `len` by requiring exactly one position to match it.
+    pub(crate) fn scan_write(
+        &mut self,
+        block_id: BlockId,
+        len: usize,
+        index: AcirVar,
+        value: AcirVar,
+    ) -> Result<(), RuntimeError> {
+        let mut num_matches = self.add_constant(F::zero());
+        for i in 0..len {
+            let position = self.add_constant(i);
+            let is_match = self.eq_var(position, index)?;
+            self.conditional_write_to_memory(block_id, &position, &value, is_match)?;
+            num_matches = self.add_var(num_matches, is_match)?;
+        }
+
+        let one = self.add_constant(F::one());
+        self.assert_eq_var(num_matches, one, None)
+    }
+
+    /// Reads the value at the (possibly symbolic) `index` of the first `len` slots of memory `block_id`
+    /// by scanning over every slot and summing each value weighted by whether its position matches `index`.
+    ///
+    /// `index` is constrained to be less than `len` by requiring exactly one position to match it.
+    /// A constant `index` is read directly from memory instead.
+    pub(crate) fn scan_read(
+        &mut self,
+        block_id: BlockId,
+        len: usize,
+        index: AcirVar,
+    ) -> Result<AcirVar, RuntimeError> {
+        if let Some(constant_index) = self.var_to_expression(index)?.to_const().copied() {
+            if constant_index.try_into_u128().is_none_or(|index| index >= len as u128) {
+                return Err(RuntimeError::InternalError(InternalError::General {
+                    message: format!("index {constant_index} is out of bounds for length {len}"),
+                    call_stack: self.get_call_stack(),
+                }));
+            }
+            return Ok(self.read_from_memory(block_id, &index)?);
+        }
+
+        let mut num_matches = self.add_constant(F::zero());
+        let mut result = self.add_constant(F::zero());
+        for i in 0..len {
+            let position = self.add_constant(i);
+            let is_match = self.eq_var(position, index)?;
+            let value = self.read_from_memory(block_id, &position)?;
+            let selected_value = self.mul_var(value, is_match)?;
+            result = self.add_var(result, selected_value)?;
+            num_matches = self.add_var(num_matches, is_match)?;
+        }
+
+        let one = self.add_constant(F::one());
+        self.assert_eq_var(num_matches, one, None)?;
+        Ok(result)
+    }
+
+    /// Returns `var mod modulus` for the unsigned `bit_size`-bit value `var` and a non-zero constant `modulus`.
+    pub(crate) fn reduce_mod_constant(
+        &mut self,
+        var: AcirVar,
+        modulus: usize,
+        bit_size: u32,
+    ) -> Result<AcirVar, RuntimeError> {
+        if modulus == 0 {
+            return Err(RuntimeError::InternalError(InternalError::General {
+                message: "cannot reduce modulo zero".to_string(),
+                call_stack: self.get_call_stack(),
+            }));
+        }
+        let modulus = self.add_constant(modulus);
+        let one = self.add_constant(F::one());
+        let (_, remainder) = self.euclidean_division_var(var, modulus, bit_size, one)?;
+        Ok(remainder)
+    }
+
+    /// Writes the first `len` slots of memory `block_id` rotated left by `amount` into memory `out_block`,
+    /// i.e. `out_block[i] = block_id[(i + amount) % len]`.
+    ///
+    /// `amount` is constrained to fit in as many bits as `len`, and `out_block` must already be initialized with
+    /// at least `len` slots. Each read scans over every slot of `block_id`, unless `amount` is constant.
+    pub(crate) fn rotate_array(
+        &mut self,
+        block_id: BlockId,
+        len: usize,
+        amount: AcirVar,
+        out_block: BlockId,
+    ) -> Result<(), RuntimeError> {
+        if len == 0 {
+            return Ok(());
+        }
+
+        let amount_bit_size = usize::BITS - len.leading_zeros();
+        let one = self.add_constant(F::one());
+        let amount = self.range_constrain_var(
+            amount,
+            &NumericType::Unsigned { bit_size: amount_bit_size },
+            None,
+            one,
+        )?;
+
+        // Once `amount` is reduced below `len`, `i + amount` wraps around at most once.
+        let amount = self.reduce_mod_constant(amount, len, amount_bit_size)?;
+        let len_var = self.add_constant(len);
+        let index_bit_size = usize::BITS - (2 * len - 1).leading_zeros();
+        for i in 0..len {
+            let position = self.add_constant(i);
+            let index = self.add_var(position, amount)?;
+            let wraps = self.more_than_eq_var(index, len_var, index_bit_size)?;
+            let index = self.add_mul_var(index, -F::from(len as u128), wraps)?;
+            let value = self.scan_read(block_id, len, index)?;
+            self.write_to_memory(out_block, &position, &value)?;
+        }
+        Ok(())
+    }
+
+    /// Returns the sum of the first `len` slots of memory `block_id`, asserting along the way that the
+    /// sum of the first `index` slots equals `expected` for each `(index, expected)` in `checkpoints`.
+    pub(crate) fn checkpointed_sum(
+        &mut self,
+        block_id: BlockId,
+        len: usize,
+        checkpoints: &[(usize, AcirVar)],
+    ) -> Result<AcirVar, RuntimeError> {
+        if let Some((index, _)) = checkpoints.iter().find(|(index, _)| *index > len) {
+            return Err(RuntimeError::InternalError(InternalError::General {
+                message: format!("checkpoint {index} is out of bounds for length {len}"),
+                call_stack: self.get_call_stack(),
+            }));
+        }
+
+        let mut sum = self.add_constant(F::zero());
+        for i in 0..=len {
+            for (_, expected) in checkpoints.iter().filter(|(index, _)| *index == i) {
+                self.assert_eq_var(sum, *expected, None)?;
+            }
+            if i < len {
+                let position = self.add_constant(i);
+                let value = self.read_from_memory(block_id, &position)?;
+                sum = self.add_var(sum, value)?;
+            }
+        }
+        Ok(sum)
+    }
+
+    /// Constrains the first `prefix_len` slots of memories `a` and `b` to be equal when `predicate` is true.
+    ///
+    /// The lengths of memory blocks aren't tracked, so it is up to the caller to ensure both blocks
+    /// hold at least `prefix_len` slots.
+    pub(crate) fn assert_prefix_eq(
+        &mut self,
+        a: BlockId,
+        b: BlockId,
+        prefix_len: usize,
+        predicate: AcirVar,
+    ) -> Result<(), RuntimeError> {
+        let zero = self.add_constant(F::zero());
+        for i in 0..prefix_len {
+            let position = self.add_constant(i);
+            let a_value = self.read_from_memory(a, &position)?;
+            let b_value = self.read_from_memory(b, &position)?;
+            let diff = self.sub_var(a_value, b_value)?;
+            let predicated_diff = self.mul_var(diff, predicate)?;
+            self.assert_eq_var(predicated_diff, zero, None)?;
+        }
+        Ok(())
+    }
+
+    /// Folds the first `len` slots of memory `block_id` into a single value, by reading each slot in
+    /// order and combining it with the accumulated value (starting from `init`) using `op`.
+    pub(crate) fn fold_memory(
+        &mut self,
+        block_id: BlockId,
+        len: usize,
+        init: AcirVar,
+        mut op: impl FnMut(&mut Self, AcirVar, AcirVar) -> Result<AcirVar, RuntimeError>,
+    ) -> Result<AcirVar, RuntimeError> {
+        let mut acc = init;
+        for i in 0..len {
+            let position = self.add_constant(i);
+            let value = self.read_from_memory(block_id, &position)?;
+            acc = op(self, acc, value)?;
+        }
+        Ok(acc)
+    }
+
+    /// Writes the product of the `rows` x `cols` matrix stored row-major in memory `matrix` with the
+    /// `cols`-long vector in memory `vector` to the first `rows` slots of `out_block`.
+    ///
+    /// All indices are known at compile-time, so no index arithmetic is emitted into the circuit.
+    pub(crate) fn matrix_vector_mul(
+        &mut self,
+        matrix: BlockId,
+        vector: BlockId,
+        rows: usize,
+        cols: usize,
+        out_block: BlockId,
+    ) -> Result<(), RuntimeError> {
+        let vector_values = try_vecmap(0..cols, |col| {
+            let position = self.add_constant(col);
+            self.read_from_memory(vector, &position)
+        })?;
+        for row in 0..rows {
+            let mut dot_product = self.add_constant(F::zero());
+            for (col, vector_value) in vector_values.iter().enumerate() {
+                let position = self.add_constant(row * cols + col);
+                let matrix_value = self.read_from_memory(matrix, &position)?;
+                let product = self.mul_var(matrix_value, *vector_value)?;
+                dot_product = self.add_var(dot_product, product)?;
+            }
+            let position = self.add_constant(row);
+            self.write_to_memory(out_block, &position, &dot_product)?;
+        }
+        Ok(())
+    }
+
+    /// Returns the entry of the constant `table` at the index whose little-endian bits are `index_bits`.
+    ///
+    /// The entry is selected by a balanced tree of selections, where each level of the tree halves the
+    /// candidate entries using the next bit of the index. Entries past the end of `table` are zero.
+    pub(crate) fn binary_tree_select(
+        &mut self,
+        index_bits: &[AcirVar],
+        table: &[F],
+    ) -> Result<AcirVar, RuntimeError> {
+        let max_len = 1_u128.checked_shl(index_bits.len() as u32).unwrap_or(u128::MAX);
+        if table.len() as u128 > max_len {
+            return Err(RuntimeError::InternalError(InternalError::General {
+                message: format!(
+                    "cannot index a table of length {} with {} bits",
+                    table.len(),
+                    index_bits.len()
+                ),
+                call_stack: self.get_call_stack(),
+            }));
+        }
+
+        let zero = self.add_constant(F::zero());
+        let mut entries = vecmap(table, |entry| self.add_constant(*entry));
+        if entries.is_empty() {
+            entries.push(zero);
+        }
+        for bit in index_bits {
+            entries = try_vecmap(entries.chunks(2), |pair| {
+                let high = pair.get(1).copied().unwrap_or(zero);
+                self.select_var(*bit, high, pair[0])
+            })?;
+        }
+        Ok(entries[0])
+    }
+
+    /// Returns `values[index]` for a (possibly symbolic) `index`, by summing each value weighted by
+    /// whether its position matches `index`.
+    ///
+    /// `index` is constrained to be less than the number of values by requiring exactly one position
+    /// to match it. A constant `index` selects its value directly.
+    pub(crate) fn select_n(
+        &mut self,
+        index: AcirVar,
+        values: &[AcirVar],
+    ) -> Result<AcirVar, RuntimeError> {
+        if values.is_empty() {
+            return Err(RuntimeError::InternalError(InternalError::General {
+                message: "cannot select from an empty list of values".to_string(),
+                call_stack: self.get_call_stack(),
+            }));
+        }
+        if let Some(constant_index) = self.var_to_expression(index)?.to_const().copied() {
+            let value = constant_index
+                .try_into_u128()
+                .and_then(|index| values.get(usize::try_from(index).ok()?));
+            return value.copied().ok_or_else(|| {
+                RuntimeError::InternalError(InternalError::General {
+                    message: format!(
+                        "index {constant_index} is out of bounds for length {}",
+                        values.len()
+                    ),
+                    call_stack: self.get_call_stack(),
+                })
+            });
+        }
+
+        let mut num_matches = self.add_constant(F::zero());
+        let mut result = self.add_constant(F::zero());
+        for (i, value) in values.iter().enumerate() {
+            let position = self.add_constant(i);
+            let is_match = self.eq_var(position, index)?;
+            let selected_value = self.mul_var(*value, is_match)?;
+            result = self.add_var(result, selected_value)?;
+            num_matches = self.add_var(num_matches, is_match)?;
+        }
+
+        let one = self.add_constant(F::one());
+        self.assert_eq_var(num_matches, one, None)?;
+        Ok(result)
+    }
+
+    /// Returns `(index, valid)`, where `index` is the position of the highest set bit in `bits` and
+    /// `valid` is one if any bit is set. `index` is zero when no bit is set.
+    ///
+    /// Each entry of `bits` is assumed to be boolean.
+    pub(crate) fn priority_encode(
+        &mut self,
+        bits: &[AcirVar],
+    ) -> Result<(AcirVar, AcirVar), RuntimeError> {
+        // `seen` is the OR of the bits from the top down to the current position, so the current
+        // bit is the highest set bit exactly when `seen` flips from zero to one.
+        let mut seen = self.add_constant(F::zero());
+        let mut index = self.add_constant(F::zero());
+        for (i, bit) in bits.iter().enumerate().rev() {
+            let new_seen = self.or_var(seen, *bit, AcirType::unsigned(1))?;
+            let is_highest = self.sub_var(new_seen, seen)?;
+            index = self.add_mul_var(index, F::from(i), is_highest)?;
+            seen = new_seen;
+        }
+        Ok((index, seen))
+    }
+}
+
+/// Returns the `width`-bit CRC register `state` after feeding in `byte`, as computed by [`AcirContext::crc_update`].
+fn crc_update_constant(state: u128, byte: u128, polynomial: u128, width: u32) -> u128 {
+    let mask = u128::MAX >> (128 - width);
+    let mut state = state ^ (byte << (width - 8));
+    for _ in 0..8 {
+        let high_bit = state >> (width - 1);
+        state = ((state << 1) & mask) ^ (high_bit * polynomial);
+    }
+    state
+}
+
+/// Returns an `F` representing the value `2**power`
+///
+/// # Panics
+///
+/// Panics if `2**power` exceeds `F::modulus()`.
+pub(super) fn power_of_two<F: AcirField>(power: u32) -> F {
+    if power >= F::max_num_bits() {
+        panic!("Field cannot represent this power of two");
+    }
+    let full_bytes = power / 8;
+    let extra_bits = power % 8;
+    let most_significant_byte: u8 = match extra_bits % 8 {
+        0 => 0x01,
+        1 => 0x02,
+        2 => 0x04,
+        3 => 0x08,
+        4 => 0x10,
+        5 => 0x20,
+        6 => 0x40,
+        7 => 0x80,
+        _ => unreachable!("We cover the full range of x % 8"),
+    };
+
+    let bytes_be: Vec<u8> = std::iter::once(most_significant_byte)
+        .chain(std::iter::repeat_n(0, full_bytes as usize))
+        .collect();
+
+    F::from_be_bytes_reduce(&bytes_be)
+}
+
+/// Enum representing the possible values that a
+/// Variable can be given.
+#[derive(Debug, Eq, Clone, PartialEq)]
+enum AcirVarData<F> {
+    Witness(Witness),
+    Expr(Expression<F>),
+    Const(F),
+}
+
+impl<F> AcirVarData<F> {
+    /// Returns a FieldElement, if the underlying `AcirVarData`
+    /// represents a constant.
+    pub(crate) fn as_constant(&self) -> Option<&F> {
+        if let AcirVarData::Const(field) = self {
+            return Some(field);
+        }
+        None
+    }
+}
+
+impl<F: AcirField> AcirVarData<F> {
+    /// Converts all enum variants to an Expression.
+    pub(crate) fn to_expression(&self) -> Cow<Expression<F>> {
+        match self {
+            AcirVarData::Witness(witness) => Cow::Owned(Expression::from(*witness)),
+            AcirVarData::Expr(expr) => Cow::Borrowed(expr),
+            AcirVarData::Const(constant) => Cow::Owned(Expression::from(*constant)),
+        }
+    }
+}
+
+impl<F> From<Witness> for AcirVarData<F> {
+    fn from(witness: Witness) -> Self {
+        AcirVarData::Witness(witness)
+    }
+}
+
+impl<F: AcirField> From<Expression<F>> for AcirVarData<F> {
+    fn from(expr: Expression<F>) -> Self {
+        // Prefer simpler variants if possible.
+        if let Some(constant) = expr.to_const() {
+            AcirVarData::Const(*constant)
+        } else if let Some(witness) = expr.to_witness() {
+            AcirVarData::from(witness)
+        } else {
+            AcirVarData::Expr(expr)
+        }
+    }
+}
+
+/// Returns `op(lhs, rhs)` truncated to `bit_size` bits if both `lhs` and `rhs` are constants.
+fn fold_bitwise_constants<F: AcirField>(
+    lhs: &Expression<F>,
+    rhs: &Expression<F>,
+    bit_size: u32,
+    op: impl FnOnce(BigUint, BigUint) -> BigUint,
+) -> Option<F> {
+    let lhs = BigUint::from_bytes_be(&lhs.to_const()?.to_be_bytes());
+    let rhs = BigUint::from_bytes_be(&rhs.to_const()?.to_be_bytes());
+    let mask = BigUint::from_bytes_be(&(power_of_two::<F>(bit_size) - F::one()).to_be_bytes());
+    Some(F::from_be_bytes_reduce(&(op(lhs, rhs) & mask).to_bytes_be()))
 }
 
 /// Checks if this expression can fit into one arithmetic identity
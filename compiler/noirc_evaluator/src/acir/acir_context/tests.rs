@@ -0,0 +1,1956 @@
+use acvm::{
+    AcirField, FieldElement,
+    acir::circuit::{
+        ExpressionWidth, Opcode, OpcodeLocation,
+        brillig::{BrilligBytecode, BrilligFunctionId},
+        opcodes::{BlackBoxFuncCall, BlockId, BlockType},
+    },
+    acir::native_types::{Expression, WitnessMap},
+    pwg::{ACVM, ACVMStatus},
+};
+use bn254_blackbox_solver::Bn254BlackBoxSolver;
+use iter_extended::{try_vecmap, vecmap};
+use noirc_errors::Location;
+use num_integer::Integer;
+
+use crate::errors::RuntimeError;
+use crate::ssa::ir::{instruction::Endian, types::NumericType};
+
+use super::{AcirContext, AcirType, AcirValue, AcirVar, BrilligStdLib, BrilligStdlibFunc};
+
+type TestContext = AcirContext<FieldElement, Bn254BlackBoxSolver>;
+
+/// The Brillig stdlib functions made available to the circuits executed by [`execute`].
+/// A call to one of these functions is resolved to its position in this list.
+const STDLIB_FUNCS: [BrilligStdlibFunc; 5] = [
+    BrilligStdlibFunc::Inverse,
+    BrilligStdlibFunc::Quotient,
+    BrilligStdlibFunc::ToLeBytes,
+    BrilligStdlibFunc::Gcd,
+    BrilligStdlibFunc::LeadingZeros,
+];
+
+fn new_context() -> TestContext {
+    AcirContext::new(BrilligStdLib::default(), Bn254BlackBoxSolver::default())
+}
+
+/// Executes the circuit built up in `context`, assigning `inputs` to their variables,
+/// and returns the values of `outputs`.
+///
+/// Returns `None` if the circuit could not be solved, e.g. because a constraint failed.
+fn execute(
+    context: &mut TestContext,
+    inputs: &[(AcirVar, u128)],
+    outputs: &[AcirVar],
+) -> Option<Vec<FieldElement>> {
+    let output_witnesses = try_vecmap(outputs, |output| context.var_to_witness(*output)).unwrap();
+    let mut initial_witness = WitnessMap::new();
+    for (input, value) in inputs {
+        initial_witness.insert(context.var_to_witness(*input).unwrap(), FieldElement::from(*value));
+    }
+
+    let stdlib_locations = context.acir_ir.brillig_stdlib_func_locations.clone();
+    for (location, func) in stdlib_locations {
+        let index = STDLIB_FUNCS.iter().position(|stdlib_func| *stdlib_func == func).unwrap();
+        context.acir_ir.resolve_brillig_stdlib_call(location, BrilligFunctionId(index as u32));
+    }
+    let brillig_functions = vecmap(STDLIB_FUNCS, |func| BrilligBytecode {
+        bytecode: context.brillig_stdlib.get_code(func).byte_code.clone(),
+    });
+
+    let opcodes = context.acir_ir.opcodes();
+    let mut acvm =
+        ACVM::new(&context.blackbox_solver, opcodes, initial_witness, &brillig_functions, &[]);
+    if acvm.solve() != ACVMStatus::Solved {
+        return None;
+    }
+    let witness_map = acvm.finalize();
+    Some(vecmap(output_witnesses, |witness| witness_map[&witness]))
+}
+
+/// Initializes memory `block_id` with the given constant `values`.
+fn initialize_constant_array(context: &mut TestContext, block_id: BlockId, values: &[u128]) {
+    let values = vecmap(values, |value| {
+        AcirValue::Var(context.add_constant(FieldElement::from(*value)), AcirType::field())
+    });
+    context
+        .initialize_array(
+            block_id,
+            values.len(),
+            Some(AcirValue::Array(values.into())),
+            BlockType::Memory,
+        )
+        .unwrap();
+}
+
+/// Reads the first `len` slots of memory `block_id`.
+fn read_array(context: &mut TestContext, block_id: BlockId, len: usize) -> Vec<AcirVar> {
+    vecmap(0..len, |i| {
+        let index = context.add_constant(i);
+        context.read_from_memory(block_id, &index).unwrap()
+    })
+}
+
+/// Returns the number of memory operations in `context` from the `start`th opcode onwards.
+fn count_memory_ops(context: &TestContext, start: usize) -> usize {
+    context.acir_ir.opcodes()[start..]
+        .iter()
+        .filter(|opcode| matches!(opcode, Opcode::MemoryOp { .. }))
+        .count()
+}
+
+fn fields(values: &[u128]) -> Vec<FieldElement> {
+    vecmap(values, |value| FieldElement::from(*value))
+}
+
+#[test]
+fn initialize_array_prefix_pads_with_default() {
+    let mut context = new_context();
+    let block_id = BlockId(0);
+
+    let x = context.add_variable();
+    let y = context.add_variable();
+    let values = vec![AcirValue::Var(x, AcirType::field()), AcirValue::Var(y, AcirType::field())];
+    let zero = context.add_constant(FieldElement::zero());
+    context.initialize_array_prefix(block_id, &values, 4, zero, BlockType::Memory).unwrap();
+
+    let x_witness = context.var_to_witness(x).unwrap();
+    let y_witness = context.var_to_witness(y).unwrap();
+    let zero_witness = context.var_to_witness(zero).unwrap();
+    let opcodes = context.acir_ir.opcodes();
+    let Some(Opcode::MemoryInit { block_id: init_block_id, init, .. }) = opcodes.last() else {
+        panic!("expected a memory init opcode");
+    };
+    assert_eq!(*init_block_id, block_id);
+    assert_eq!(init, &vec![x_witness, y_witness, zero_witness, zero_witness]);
+}
+
+#[test]
+fn initialize_array_prefix_rejects_too_many_values() {
+    let mut context = new_context();
+
+    let values = vecmap(0..3, |_| AcirValue::Var(context.add_variable(), AcirType::field()));
+    let zero = context.add_constant(FieldElement::zero());
+    let result = context.initialize_array_prefix(BlockId(0), &values, 2, zero, BlockType::Memory);
+    assert!(result.is_err());
+}
+
+#[test]
+fn scan_write_at_symbolic_index() {
+    let mut context = new_context();
+    let block_id = BlockId(0);
+    initialize_constant_array(&mut context, block_id, &[10, 20, 30, 40]);
+
+    let index = context.add_variable();
+    let value = context.add_constant(FieldElement::from(99_u128));
+    context.scan_write(block_id, 4, index, value).unwrap();
+    let outputs = read_array(&mut context, block_id, 4);
+
+    let result = execute(&mut context, &[(index, 2)], &outputs);
+    assert_eq!(result, Some(fields(&[10, 20, 99, 40])));
+}
+
+#[test]
+fn scan_write_rejects_out_of_bounds_index() {
+    let mut context = new_context();
+    let block_id = BlockId(0);
+    initialize_constant_array(&mut context, block_id, &[10, 20, 30, 40]);
+
+    let index = context.add_variable();
+    let value = context.add_constant(FieldElement::from(99_u128));
+    context.scan_write(block_id, 4, index, value).unwrap();
+
+    assert_eq!(execute(&mut context, &[(index, 4)], &[]), None);
+}
+
+#[test]
+fn scan_read_constant_index_reads_directly() {
+    let mut context = new_context();
+    let block_id = BlockId(0);
+    initialize_constant_array(&mut context, block_id, &[10, 20, 30, 40]);
+    let num_opcodes = context.acir_ir.opcodes().len();
+
+    let index = context.add_constant(FieldElement::from(1_u128));
+    let result = context.scan_read(block_id, 4, index).unwrap();
+
+    let num_memory_ops = count_memory_ops(&context, num_opcodes);
+    assert_eq!(num_memory_ops, 1);
+    assert_eq!(execute(&mut context, &[], &[result]), Some(fields(&[20])));
+}
+
+#[test]
+fn scan_read_symbolic_index_scans_every_slot() {
+    let mut context = new_context();
+    let block_id = BlockId(0);
+    initialize_constant_array(&mut context, block_id, &[10, 20, 30, 40]);
+    let num_opcodes = context.acir_ir.opcodes().len();
+
+    let index = context.add_variable();
+    let result = context.scan_read(block_id, 4, index).unwrap();
+
+    let num_memory_ops = count_memory_ops(&context, num_opcodes);
+    assert_eq!(num_memory_ops, 4);
+    assert_eq!(execute(&mut context, &[(index, 3)], &[result]), Some(fields(&[40])));
+    assert_eq!(execute(&mut context, &[(index, 4)], &[result]), None);
+}
+
+#[test]
+fn gcd_of_constants() {
+    let mut context = new_context();
+    let one = context.add_constant(FieldElement::one());
+    for (lhs, rhs) in [(12_u128, 18_u128), (17, 5), (0, 7), (0, 0)] {
+        let lhs_var = context.add_constant(FieldElement::from(lhs));
+        let rhs_var = context.add_constant(FieldElement::from(rhs));
+        let result = context.gcd(lhs_var, rhs_var, 32, one).unwrap();
+        assert_eq!(context.constant(result), &FieldElement::from(lhs.gcd(&rhs)));
+    }
+}
+
+#[test]
+fn gcd_matches_rust_gcd() {
+    let mut context = new_context();
+    let lhs = context.add_variable();
+    let rhs = context.add_variable();
+    let one = context.add_constant(FieldElement::one());
+    let result = context.gcd(lhs, rhs, 32, one).unwrap();
+
+    for (lhs_value, rhs_value) in
+        [(12_u128, 18_u128), (18, 12), (17, 5), (0, 7), (7, 0), (0, 0), (4_294_967_295, 65_535)]
+    {
+        let expected = lhs_value.gcd(&rhs_value);
+        let result = execute(&mut context, &[(lhs, lhs_value), (rhs, rhs_value)], &[result]);
+        assert_eq!(result, Some(fields(&[expected])));
+    }
+}
+
+#[test]
+fn byte_reverse_of_constants() {
+    let mut context = new_context();
+    for value in [0_u32, 1, 0x1234_5678, 0xdead_beef, u32::MAX] {
+        let var = context.add_constant(FieldElement::from(u128::from(value)));
+        let result = context.byte_reverse(var, 4).unwrap();
+        assert_eq!(context.constant(result), &FieldElement::from(u128::from(value.swap_bytes())));
+    }
+}
+
+#[test]
+fn byte_reverse_of_variable() {
+    let mut context = new_context();
+    let var = context.add_variable();
+    let result = context.byte_reverse(var, 4).unwrap();
+
+    for value in [0x1234_5678_u32, 0xdead_beef] {
+        let result = execute(&mut context, &[(var, u128::from(value))], &[result]);
+        assert_eq!(result, Some(fields(&[u128::from(value.swap_bytes())])));
+    }
+}
+
+#[test]
+fn binary_tree_select_with_symbolic_bits() {
+    let mut context = new_context();
+    let table = fields(&[3, 1, 4, 1, 5, 9, 2, 6]);
+    let index_bits = vecmap(0..3, |_| context.add_variable());
+    let result = context.binary_tree_select(&index_bits, &table).unwrap();
+
+    for (index, expected) in table.iter().enumerate() {
+        let inputs = vecmap(0..3, |bit| (index_bits[bit], ((index >> bit) & 1) as u128));
+        assert_eq!(execute(&mut context, &inputs, &[result]), Some(vec![*expected]));
+    }
+}
+
+#[test]
+fn binary_tree_select_with_constant_bits() {
+    let mut context = new_context();
+    let table = fields(&[3, 1, 4, 1, 5]);
+    let one = context.add_constant(FieldElement::one());
+    let zero = context.add_constant(FieldElement::zero());
+
+    // Index 4 is 0b100
+    let result = context.binary_tree_select(&[zero, zero, one], &table).unwrap();
+    assert_eq!(context.constant(result), &table[4]);
+
+    // Index 6 is past the end of the table
+    let result = context.binary_tree_select(&[zero, one, one], &table).unwrap();
+    assert_eq!(context.constant(result), &FieldElement::zero());
+
+    assert!(context.binary_tree_select(&[zero, one], &table).is_err());
+}
+
+/// Reference Morton encoder interleaving the low `bits_each` bits of `x` and `y`.
+fn morton_encode(x: u128, y: u128, bits_each: u32) -> u128 {
+    (0..bits_each)
+        .fold(0, |acc, i| acc | (((x >> i) & 1) << (2 * i)) | (((y >> i) & 1) << (2 * i + 1)))
+}
+
+#[test]
+fn morton_interleave_of_constants() {
+    let mut context = new_context();
+    for (x, y) in [(0_u128, 0_u128), (1, 0), (0, 1), (0b1011, 0b0110), (15, 15)] {
+        let x_var = context.add_constant(FieldElement::from(x));
+        let y_var = context.add_constant(FieldElement::from(y));
+        let result = context.morton_interleave(x_var, y_var, 4).unwrap();
+        assert_eq!(context.constant(result), &FieldElement::from(morton_encode(x, y, 4)));
+    }
+}
+
+#[test]
+fn morton_interleave_of_variables() {
+    let mut context = new_context();
+    let x = context.add_variable();
+    let y = context.add_variable();
+    let result = context.morton_interleave(x, y, 4).unwrap();
+
+    for (x_value, y_value) in [(0_u128, 0_u128), (1, 0), (0, 1), (0b1011, 0b0110), (15, 15)] {
+        let result = execute(&mut context, &[(x, x_value), (y, y_value)], &[result]);
+        assert_eq!(result, Some(fields(&[morton_encode(x_value, y_value, 4)])));
+    }
+    assert_eq!(execute(&mut context, &[(x, 16), (y, 0)], &[result]), None);
+}
+
+#[test]
+fn accumulate_or_reset_with_constant_reset() {
+    let mut context = new_context();
+    let acc = context.add_constant(FieldElement::from(5_u128));
+    let value = context.add_constant(FieldElement::from(3_u128));
+
+    let zero = context.add_constant(FieldElement::zero());
+    let result = context.accumulate_or_reset(acc, value, zero).unwrap();
+    assert_eq!(context.constant(result), &FieldElement::from(8_u128));
+
+    let one = context.add_constant(FieldElement::one());
+    let result = context.accumulate_or_reset(acc, value, one).unwrap();
+    assert_eq!(result, value);
+}
+
+#[test]
+fn accumulate_or_reset_with_symbolic_reset() {
+    let mut context = new_context();
+    let acc = context.add_variable();
+    let value = context.add_variable();
+    let reset = context.add_variable();
+    let result = context.accumulate_or_reset(acc, value, reset).unwrap();
+
+    // `acc + value - reset * acc`
+    let acc_witness = context.var_to_witness(acc).unwrap();
+    let value_witness = context.var_to_witness(value).unwrap();
+    let reset_witness = context.var_to_witness(reset).unwrap();
+    let mut expected = Expression::default();
+    expected.push_multiplication_term(-FieldElement::one(), acc_witness, reset_witness);
+    expected.push_addition_term(FieldElement::one(), acc_witness);
+    expected.push_addition_term(FieldElement::one(), value_witness);
+    assert_eq!(context.var_to_expression(result).unwrap(), expected);
+
+    let inputs = [(acc, 5), (value, 3), (reset, 0)];
+    assert_eq!(execute(&mut context, &inputs, &[result]), Some(fields(&[8])));
+    let inputs = [(acc, 5), (value, 3), (reset, 1)];
+    assert_eq!(execute(&mut context, &inputs, &[result]), Some(fields(&[3])));
+}
+
+#[test]
+fn running_max_without_reset() {
+    let mut context = new_context();
+    let acc = context.add_variable();
+    let value = context.add_variable();
+    let zero = context.add_constant(FieldElement::zero());
+    let result = context.running_max(acc, value, zero, AcirType::unsigned(8)).unwrap();
+
+    for (acc_value, value_value) in [(5_u128, 3_u128), (3, 5), (7, 7), (0, 255)] {
+        let inputs = [(acc, acc_value), (value, value_value)];
+        let expected = fields(&[acc_value.max(value_value)]);
+        assert_eq!(execute(&mut context, &inputs, &[result]), Some(expected));
+    }
+}
+
+#[test]
+fn running_max_with_reset() {
+    let mut context = new_context();
+    let acc = context.add_variable();
+    let value = context.add_variable();
+    let one = context.add_constant(FieldElement::one());
+    let result = context.running_max(acc, value, one, AcirType::unsigned(8)).unwrap();
+    assert_eq!(result, value);
+}
+
+#[test]
+fn saturating_mul_of_constants() {
+    let mut context = new_context();
+    for (lhs, rhs) in [(0_u128, 200_u128), (15, 17), (16, 16), (255, 255)] {
+        let lhs_var = context.add_constant(FieldElement::from(lhs));
+        let rhs_var = context.add_constant(FieldElement::from(rhs));
+        let result = context.saturating_mul(lhs_var, rhs_var, 8).unwrap();
+        assert_eq!(context.constant(result), &FieldElement::from((lhs * rhs).min(255)));
+    }
+}
+
+#[test]
+fn saturating_mul_of_variables() {
+    let mut context = new_context();
+    let lhs = context.add_variable();
+    let rhs = context.add_variable();
+    let result = context.saturating_mul(lhs, rhs, 8).unwrap();
+
+    for (lhs_value, rhs_value) in [(0_u128, 200_u128), (15, 17), (16, 16), (128, 2), (255, 255)] {
+        let result = execute(&mut context, &[(lhs, lhs_value), (rhs, rhs_value)], &[result]);
+        assert_eq!(result, Some(fields(&[(lhs_value * rhs_value).min(255)])));
+    }
+}
+
+#[test]
+fn saturating_mul_rejects_large_bit_sizes() {
+    let mut context = new_context();
+    let lhs = context.add_variable();
+    let rhs = context.add_variable();
+    assert!(context.saturating_mul(lhs, rhs, 127).is_err());
+}
+
+#[test]
+fn abs_diff_of_constants() {
+    let mut context = new_context();
+    for (a, b) in [(200_u128, 55_u128), (55, 200), (42, 42)] {
+        let a_var = context.add_constant(FieldElement::from(a));
+        let b_var = context.add_constant(FieldElement::from(b));
+        let result = context.abs_diff(a_var, b_var, 8).unwrap();
+        assert_eq!(context.constant(result), &FieldElement::from(a.abs_diff(b)));
+    }
+}
+
+#[test]
+fn abs_diff_of_variables() {
+    let mut context = new_context();
+    let a = context.add_variable();
+    let b = context.add_variable();
+    let result = context.abs_diff(a, b, 8).unwrap();
+
+    for (a_value, b_value) in [(200_u128, 55_u128), (55, 200), (42, 42), (0, 255)] {
+        let result = execute(&mut context, &[(a, a_value), (b, b_value)], &[result]);
+        assert_eq!(result, Some(fields(&[a_value.abs_diff(b_value)])));
+    }
+}
+
+#[test]
+fn lerp_of_constants() {
+    let mut context = new_context();
+    let ten = context.add_constant(FieldElement::from(10_u128));
+    let twenty = context.add_constant(FieldElement::from(20_u128));
+    for (t, expected) in [(0_u128, 10_u128), (128, 15), (256, 20)] {
+        let t = context.add_constant(FieldElement::from(t));
+        let result = context.lerp(ten, twenty, t, 8).unwrap();
+        assert_eq!(context.constant(result), &FieldElement::from(expected));
+    }
+
+    // Decreasing interpolations round towards negative infinity
+    for (t, expected) in [(0_u128, 20_u128), (64, 17), (128, 15), (256, 10)] {
+        let t = context.add_constant(FieldElement::from(t));
+        let result = context.lerp(twenty, ten, t, 8).unwrap();
+        assert_eq!(context.constant(result), &FieldElement::from(expected));
+    }
+}
+
+#[test]
+fn lerp_of_variables() {
+    let mut context = new_context();
+    let a = context.add_variable();
+    let b = context.add_variable();
+    let t = context.add_variable();
+    let result = context.lerp(a, b, t, 8).unwrap();
+
+    for (a_value, b_value, t_value, expected) in
+        [(10, 20, 0, 10), (10, 20, 128, 15), (10, 20, 256, 20), (20, 10, 64, 17)]
+    {
+        let inputs = [(a, a_value), (b, b_value), (t, t_value)];
+        assert_eq!(execute(&mut context, &inputs, &[result]), Some(fields(&[expected])));
+    }
+}
+
+#[test]
+fn overflowing_add_of_constants() {
+    let mut context = new_context();
+    for (lhs, rhs) in [(0_u8, 0_u8), (100, 155), (u8::MAX, 1), (200, 100), (u8::MAX, u8::MAX)] {
+        let lhs_var = context.add_constant(FieldElement::from(lhs as u128));
+        let rhs_var = context.add_constant(FieldElement::from(rhs as u128));
+        let (wrapped, overflow) = context.overflowing_add(lhs_var, rhs_var, 8).unwrap();
+
+        let (expected_wrapped, expected_overflow) = lhs.overflowing_add(rhs);
+        assert_eq!(context.constant(wrapped), &FieldElement::from(expected_wrapped as u128));
+        assert_eq!(context.constant(overflow), &FieldElement::from(expected_overflow));
+    }
+}
+
+#[test]
+fn overflowing_add_of_variables() {
+    let mut context = new_context();
+    let lhs = context.add_variable();
+    let rhs = context.add_variable();
+    let (wrapped, overflow) = context.overflowing_add(lhs, rhs, 8).unwrap();
+
+    for (lhs_value, rhs_value) in [(0_u8, 0_u8), (100, 155), (u8::MAX, 1), (u8::MAX, u8::MAX)] {
+        let inputs = [(lhs, lhs_value as u128), (rhs, rhs_value as u128)];
+        let (expected_wrapped, expected_overflow) = lhs_value.overflowing_add(rhs_value);
+        let expected = fields(&[expected_wrapped as u128, expected_overflow as u128]);
+        assert_eq!(execute(&mut context, &inputs, &[wrapped, overflow]), Some(expected));
+    }
+}
+
+#[test]
+fn popcount_greater_of_constants() {
+    let mut context = new_context();
+    for (lhs, rhs) in [(0_u8, 0_u8), (0b1011, 0b0110), (0b0110, 0b1011), (u8::MAX, 0x7f), (1, 0x80)]
+    {
+        let lhs_var = context.add_constant(FieldElement::from(lhs as u128));
+        let rhs_var = context.add_constant(FieldElement::from(rhs as u128));
+        let result = context.popcount_greater(lhs_var, rhs_var, 8).unwrap();
+        let expected = lhs.count_ones() > rhs.count_ones();
+        assert_eq!(context.constant(result), &FieldElement::from(expected));
+    }
+}
+
+#[test]
+fn popcount_greater_of_variables() {
+    let mut context = new_context();
+    let lhs = context.add_variable();
+    let rhs = context.add_variable();
+    let result = context.popcount_greater(lhs, rhs, 8).unwrap();
+
+    for (lhs_value, rhs_value) in
+        [(0_u8, 0_u8), (0b1011, 0b0110), (0b0110, 0b1011), (u8::MAX, 0x7f)]
+    {
+        let inputs = [(lhs, lhs_value as u128), (rhs, rhs_value as u128)];
+        let expected = lhs_value.count_ones() > rhs_value.count_ones();
+        assert_eq!(execute(&mut context, &inputs, &[result]), Some(fields(&[expected as u128])));
+    }
+}
+
+#[test]
+fn rotate_array_by_constant_amount() {
+    let mut context = new_context();
+    let (block_id, out_block) = (BlockId(0), BlockId(1));
+    initialize_constant_array(&mut context, block_id, &[10, 20, 30]);
+    initialize_constant_array(&mut context, out_block, &[0, 0, 0]);
+    let num_opcodes = context.acir_ir.opcodes().len();
+
+    let amount = context.add_constant(FieldElement::one());
+    context.rotate_array(block_id, 3, amount, out_block).unwrap();
+
+    // One read and one write per slot
+    let num_memory_ops = count_memory_ops(&context, num_opcodes);
+    assert_eq!(num_memory_ops, 6);
+    let outputs = read_array(&mut context, out_block, 3);
+    assert_eq!(execute(&mut context, &[], &outputs), Some(fields(&[20, 30, 10])));
+}
+
+#[test]
+fn rotate_array_by_symbolic_amount() {
+    let mut context = new_context();
+    let (block_id, out_block) = (BlockId(0), BlockId(1));
+    initialize_constant_array(&mut context, block_id, &[10, 20, 30]);
+    initialize_constant_array(&mut context, out_block, &[0, 0, 0]);
+
+    let amount = context.add_variable();
+    context.rotate_array(block_id, 3, amount, out_block).unwrap();
+    let outputs = read_array(&mut context, out_block, 3);
+
+    assert_eq!(execute(&mut context, &[(amount, 0)], &outputs), Some(fields(&[10, 20, 30])));
+    assert_eq!(execute(&mut context, &[(amount, 2)], &outputs), Some(fields(&[30, 10, 20])));
+    assert_eq!(execute(&mut context, &[(amount, 3)], &outputs), Some(fields(&[10, 20, 30])));
+}
+
+#[test]
+fn rotate_array_rejects_amount_wider_than_length() {
+    let mut context = new_context();
+    let (block_id, out_block) = (BlockId(0), BlockId(1));
+    initialize_constant_array(&mut context, block_id, &[10, 20, 30]);
+    initialize_constant_array(&mut context, out_block, &[0, 0, 0]);
+
+    let amount = context.add_variable();
+    context.rotate_array(block_id, 3, amount, out_block).unwrap();
+    let outputs = read_array(&mut context, out_block, 3);
+
+    // The length 3 fits in 2 bits, so 4 is out of range
+    assert_eq!(execute(&mut context, &[(amount, 4)], &outputs), None);
+}
+
+#[test]
+fn inv_or_one_of_constants() {
+    let mut context = new_context();
+    let one = context.add_constant(FieldElement::one());
+
+    let zero = context.add_constant(FieldElement::zero());
+    let result = context.inv_or_one(zero, one).unwrap();
+    assert_eq!(context.constant(result), &FieldElement::one());
+
+    let five = context.add_constant(FieldElement::from(5_u128));
+    let result = context.inv_or_one(five, one).unwrap();
+    assert_eq!(context.constant(result), &FieldElement::from(5_u128).inverse());
+}
+
+#[test]
+fn inv_or_one_of_variable() {
+    let mut context = new_context();
+    let one = context.add_constant(FieldElement::one());
+    let var = context.add_variable();
+    let result = context.inv_or_one(var, one).unwrap();
+
+    assert_eq!(execute(&mut context, &[(var, 0)], &[result]), Some(fields(&[1])));
+    let expected = vec![FieldElement::from(5_u128).inverse()];
+    assert_eq!(execute(&mut context, &[(var, 5)], &[result]), Some(expected));
+}
+
+#[test]
+fn wrapping_increment_of_constants() {
+    let mut context = new_context();
+    let one = context.add_constant(FieldElement::one());
+    let zero = context.add_constant(FieldElement::zero());
+    for capacity in [4_u128, 5] {
+        for idx in 0..capacity {
+            let idx_var = context.add_constant(FieldElement::from(idx));
+            let result = context.wrapping_increment(idx_var, capacity, 8, one).unwrap();
+            assert_eq!(context.constant(result), &FieldElement::from((idx + 1) % capacity));
+
+            let result = context.wrapping_increment(idx_var, capacity, 8, zero).unwrap();
+            assert_eq!(context.constant(result), &FieldElement::from(idx));
+        }
+    }
+}
+
+#[test]
+fn wrapping_increment_of_variables() {
+    for capacity in [4_u128, 5] {
+        let mut context = new_context();
+        let idx = context.add_variable();
+        let predicate = context.add_variable();
+        let result = context.wrapping_increment(idx, capacity, 8, predicate).unwrap();
+
+        for idx_value in 0..capacity {
+            let inputs = [(idx, idx_value), (predicate, 1)];
+            let expected = fields(&[(idx_value + 1) % capacity]);
+            assert_eq!(execute(&mut context, &inputs, &[result]), Some(expected));
+
+            let inputs = [(idx, idx_value), (predicate, 0)];
+            assert_eq!(execute(&mut context, &inputs, &[result]), Some(fields(&[idx_value])));
+        }
+    }
+}
+
+#[test]
+fn bucket_index_of_constants() {
+    let mut context = new_context();
+    let thresholds = fields(&[10, 20, 30]);
+    for (value, expected) in [(0_u128, 0_u128), (9, 0), (10, 1), (25, 2), (30, 3), (255, 3)] {
+        let var = context.add_constant(FieldElement::from(value));
+        let result = context.bucket_index(var, &thresholds, 8).unwrap();
+        assert_eq!(context.constant(result), &FieldElement::from(expected));
+    }
+}
+
+#[test]
+fn bucket_index_of_variable() {
+    let mut context = new_context();
+    let var = context.add_variable();
+    let result = context.bucket_index(var, &fields(&[10, 20, 30]), 8).unwrap();
+
+    for (value, expected) in [(0_u128, 0_u128), (9, 0), (10, 1), (25, 2), (30, 3), (255, 3)] {
+        assert_eq!(execute(&mut context, &[(var, value)], &[result]), Some(fields(&[expected])));
+    }
+}
+
+#[test]
+fn bucket_index_rejects_invalid_thresholds() {
+    let mut context = new_context();
+    let var = context.add_variable();
+    assert!(context.bucket_index(var, &fields(&[10, 30, 20]), 8).is_err());
+    assert!(context.bucket_index(var, &fields(&[10, 300]), 8).is_err());
+}
+
+#[test]
+fn dynamic_shift_left_matches_constant_shift() {
+    let mut context = new_context();
+    let var = context.add_variable();
+    let shift = context.add_variable();
+    let symbolic_result = context.dynamic_shift_left(var, shift, 7, 8).unwrap();
+
+    for shift_value in 0..=7_u32 {
+        let shift_const = context.add_constant(FieldElement::from(shift_value));
+        let constant_result = context.dynamic_shift_left(var, shift_const, 7, 8).unwrap();
+
+        let expected = fields(&[u128::from(0b1011_0101_u8 << shift_value)]);
+        let inputs = [(var, 0b1011_0101), (shift, shift_value as u128)];
+        let outputs = [symbolic_result, constant_result];
+        assert_eq!(
+            execute(&mut context, &inputs, &outputs),
+            Some([expected.clone(), expected].concat())
+        );
+    }
+    assert_eq!(execute(&mut context, &[(var, 1), (shift, 8)], &[symbolic_result]), None);
+}
+
+#[test]
+fn dynamic_shift_left_of_constants() {
+    let mut context = new_context();
+    let var = context.add_constant(FieldElement::from(0b1011_0101_u128));
+    let shift = context.add_constant(FieldElement::from(3_u128));
+    let result = context.dynamic_shift_left(var, shift, 7, 8).unwrap();
+    assert_eq!(context.constant(result), &FieldElement::from(u128::from(0b1011_0101_u8 << 3)));
+
+    let shift = context.add_constant(FieldElement::from(8_u128));
+    assert!(context.dynamic_shift_left(var, shift, 7, 8).is_err());
+}
+
+/// Evaluates the polynomial with coefficients `coeffs`, lowest degree first, at `x` term by term.
+fn eval_poly_directly(coeffs: &[u128], x: u128) -> u128 {
+    coeffs.iter().enumerate().map(|(i, coeff)| coeff * x.pow(i as u32)).sum()
+}
+
+#[test]
+fn eval_poly_of_constants() {
+    let mut context = new_context();
+    let x = context.add_constant(FieldElement::from(3_u128));
+
+    let result = context.eval_poly(&[], x).unwrap();
+    assert_eq!(context.constant(result), &FieldElement::zero());
+
+    let result = context.eval_poly(&fields(&[7]), x).unwrap();
+    assert_eq!(context.constant(result), &FieldElement::from(7_u128));
+
+    let coeffs = [5, 0, 2, 4];
+    let result = context.eval_poly(&fields(&coeffs), x).unwrap();
+    assert_eq!(context.constant(result), &FieldElement::from(eval_poly_directly(&coeffs, 3)));
+}
+
+#[test]
+fn eval_poly_of_variable() {
+    let mut context = new_context();
+    let x = context.add_variable();
+    let coeffs = [5, 0, 2, 4];
+    let result = context.eval_poly(&fields(&coeffs), x).unwrap();
+
+    for x_value in [0, 1, 2, 10, 1000] {
+        let expected = fields(&[eval_poly_directly(&coeffs, x_value)]);
+        assert_eq!(execute(&mut context, &[(x, x_value)], &[result]), Some(expected));
+    }
+}
+
+#[test]
+fn conditional_clear_memory_with_constant_condition() {
+    // A true condition writes zero to each slot directly, and a false condition emits nothing
+    for (cond, expected, expected_memory_ops) in [(true, [0, 0, 0], 3), (false, [10, 20, 30], 0)] {
+        let mut context = new_context();
+        let block_id = BlockId(0);
+        initialize_constant_array(&mut context, block_id, &[10, 20, 30]);
+        let num_opcodes = context.acir_ir.opcodes().len();
+
+        let cond = context.add_constant(FieldElement::from(cond));
+        context.conditional_clear_memory(block_id, 3, cond).unwrap();
+        assert_eq!(count_memory_ops(&context, num_opcodes), expected_memory_ops);
+
+        let outputs = read_array(&mut context, block_id, 3);
+        assert_eq!(execute(&mut context, &[], &outputs), Some(fields(&expected)));
+    }
+}
+
+#[test]
+fn conditional_clear_memory_with_symbolic_condition() {
+    let mut context = new_context();
+    let block_id = BlockId(0);
+    initialize_constant_array(&mut context, block_id, &[10, 20, 30]);
+
+    let cond = context.add_variable();
+    context.conditional_clear_memory(block_id, 3, cond).unwrap();
+    let outputs = read_array(&mut context, block_id, 3);
+
+    assert_eq!(execute(&mut context, &[(cond, 1)], &outputs), Some(fields(&[0, 0, 0])));
+    assert_eq!(execute(&mut context, &[(cond, 0)], &outputs), Some(fields(&[10, 20, 30])));
+}
+
+#[test]
+fn assert_conditional_with_constant_condition() {
+    for cond in [true, false] {
+        let mut context = new_context();
+        let one = context.add_constant(FieldElement::one());
+        let result = context.add_variable();
+        let when_true = context.add_variable();
+        let when_false = context.add_variable();
+        let cond_var = context.add_constant(FieldElement::from(cond));
+        let num_opcodes = context.acir_ir.opcodes().len();
+        context.assert_conditional(result, cond_var, when_true, when_false, one).unwrap();
+
+        // Only the equality with the selected branch is asserted
+        assert_eq!(context.acir_ir.opcodes().len() - num_opcodes, 1);
+        let (selected, other) = if cond { (10, 20) } else { (20, 10) };
+        let inputs = [(result, 10), (when_true, selected), (when_false, other)];
+        assert_eq!(execute(&mut context, &inputs, &[]), Some(vec![]));
+        let inputs = [(result, 10), (when_true, other), (when_false, selected)];
+        assert_eq!(execute(&mut context, &inputs, &[]), None);
+    }
+}
+
+#[test]
+fn assert_conditional_with_symbolic_condition() {
+    let mut context = new_context();
+    let predicate = context.add_variable();
+    let result = context.add_variable();
+    let cond = context.add_variable();
+    let when_true = context.add_variable();
+    let when_false = context.add_variable();
+    context.assert_conditional(result, cond, when_true, when_false, predicate).unwrap();
+
+    let inputs = |predicate_value, result_value, cond_value| {
+        [
+            (predicate, predicate_value),
+            (result, result_value),
+            (cond, cond_value),
+            (when_true, 10),
+            (when_false, 20),
+        ]
+    };
+    assert_eq!(execute(&mut context, &inputs(1, 10, 1), &[]), Some(vec![]));
+    assert_eq!(execute(&mut context, &inputs(1, 20, 0), &[]), Some(vec![]));
+    assert_eq!(execute(&mut context, &inputs(1, 20, 1), &[]), None);
+    assert_eq!(execute(&mut context, &inputs(1, 10, 0), &[]), None);
+    // Nothing is asserted when the predicate is false
+    assert_eq!(execute(&mut context, &inputs(0, 30, 1), &[]), Some(vec![]));
+}
+
+#[test]
+fn checkpointed_sum_asserts_partial_sums() {
+    let mut context = new_context();
+    let block_id = BlockId(0);
+    initialize_constant_array(&mut context, block_id, &[1, 2, 3, 4]);
+
+    let claimed = context.add_variable();
+    let total = context.checkpointed_sum(block_id, 4, &[(2, claimed)]).unwrap();
+
+    assert_eq!(execute(&mut context, &[(claimed, 3)], &[total]), Some(fields(&[10])));
+    assert_eq!(execute(&mut context, &[(claimed, 4)], &[total]), None);
+}
+
+#[test]
+fn checkpointed_sum_rejects_out_of_bounds_checkpoint() {
+    let mut context = new_context();
+    let block_id = BlockId(0);
+    initialize_constant_array(&mut context, block_id, &[1, 2, 3, 4]);
+
+    let claimed = context.add_constant(FieldElement::from(10_u128));
+    assert!(context.checkpointed_sum(block_id, 4, &[(4, claimed)]).is_ok());
+    assert!(context.checkpointed_sum(block_id, 4, &[(5, claimed)]).is_err());
+}
+
+#[test]
+fn range_membership_with_overlapping_ranges() {
+    let mut context = new_context();
+    let var = context.add_variable();
+    let ranges = [(FieldElement::from(0_u128), 10_u128.into()), (5_u128.into(), 15_u128.into())];
+    let membership = context.range_membership(var, &ranges, 8).unwrap();
+    let outputs = vecmap(membership.flatten(), |(bit, _)| bit);
+
+    assert_eq!(execute(&mut context, &[(var, 7)], &outputs), Some(fields(&[1, 1])));
+    assert_eq!(execute(&mut context, &[(var, 3)], &outputs), Some(fields(&[1, 0])));
+    assert_eq!(execute(&mut context, &[(var, 10)], &outputs), Some(fields(&[0, 1])));
+    assert_eq!(execute(&mut context, &[(var, 15)], &outputs), Some(fields(&[0, 0])));
+}
+
+#[test]
+fn range_membership_up_to_maximum_value() {
+    let mut context = new_context();
+    let var = context.add_variable();
+    let ranges = [(FieldElement::from(200_u128), 256_u128.into())];
+    let membership = context.range_membership(var, &ranges, 8).unwrap();
+    let outputs = vecmap(membership.flatten(), |(bit, _)| bit);
+
+    assert_eq!(execute(&mut context, &[(var, 255)], &outputs), Some(fields(&[1])));
+    assert_eq!(execute(&mut context, &[(var, 199)], &outputs), Some(fields(&[0])));
+}
+
+#[test]
+fn range_membership_rejects_ranges_wider_than_bits() {
+    let mut context = new_context();
+    let var = context.add_variable();
+    let ranges = [(FieldElement::from(0_u128), 257_u128.into())];
+    assert!(context.range_membership(var, &ranges, 8).is_err());
+    let ranges = [(FieldElement::from(256_u128), 256_u128.into())];
+    assert!(context.range_membership(var, &ranges, 8).is_err());
+}
+
+#[test]
+fn fold_memory_with_addition_and_multiplication() {
+    let mut context = new_context();
+    let block_id = BlockId(0);
+    initialize_constant_array(&mut context, block_id, &[2, 3, 4]);
+
+    let zero = context.add_constant(FieldElement::zero());
+    let sum =
+        context.fold_memory(block_id, 3, zero, |context, acc, value| context.add_var(acc, value));
+    let one = context.add_constant(FieldElement::one());
+    let product =
+        context.fold_memory(block_id, 3, one, |context, acc, value| context.mul_var(acc, value));
+
+    let outputs = [sum.unwrap(), product.unwrap()];
+    assert_eq!(execute(&mut context, &[], &outputs), Some(fields(&[9, 24])));
+}
+
+#[test]
+fn compare_three_way_of_constants() {
+    let mut context = new_context();
+    for (a, b) in [(3_u128, 7_u128), (5, 5), (7, 3)] {
+        let a_var = context.add_constant(FieldElement::from(a));
+        let b_var = context.add_constant(FieldElement::from(b));
+        let (is_less, is_equal, is_greater) = context.compare_three_way(a_var, b_var, 8).unwrap();
+        assert_eq!(context.constant(is_less), &FieldElement::from(a < b));
+        assert_eq!(context.constant(is_equal), &FieldElement::from(a == b));
+        assert_eq!(context.constant(is_greater), &FieldElement::from(a > b));
+    }
+}
+
+#[test]
+fn compare_three_way_of_variables() {
+    let mut context = new_context();
+    let a = context.add_variable();
+    let b = context.add_variable();
+    let (is_less, is_equal, is_greater) = context.compare_three_way(a, b, 8).unwrap();
+    let outputs = [is_less, is_equal, is_greater];
+
+    let result = execute(&mut context, &[(a, 3), (b, 7)], &outputs);
+    assert_eq!(result, Some(fields(&[1, 0, 0])));
+    let result = execute(&mut context, &[(a, 5), (b, 5)], &outputs);
+    assert_eq!(result, Some(fields(&[0, 1, 0])));
+    let result = execute(&mut context, &[(a, 7), (b, 3)], &outputs);
+    assert_eq!(result, Some(fields(&[0, 0, 1])));
+}
+
+#[test]
+fn select_n_constant_index() {
+    let mut context = new_context();
+    let values = vecmap(0..4, |_| context.add_variable());
+
+    let index = context.add_constant(FieldElement::from(2_u128));
+    assert_eq!(context.select_n(index, &values).unwrap(), values[2]);
+
+    let index = context.add_constant(FieldElement::from(4_u128));
+    assert!(context.select_n(index, &values).is_err());
+    assert!(context.select_n(index, &[]).is_err());
+}
+
+#[test]
+fn select_n_symbolic_index() {
+    let mut context = new_context();
+    let values = vecmap(0..4, |_| context.add_variable());
+    let index = context.add_variable();
+    let result = context.select_n(index, &values).unwrap();
+
+    let inputs = |index_value| {
+        let mut inputs = vec![(index, index_value)];
+        inputs.extend(values.iter().copied().zip([10, 20, 30, 40]));
+        inputs
+    };
+    assert_eq!(execute(&mut context, &inputs(3), &[result]), Some(fields(&[40])));
+    assert_eq!(execute(&mut context, &inputs(0), &[result]), Some(fields(&[10])));
+    assert_eq!(execute(&mut context, &inputs(4), &[result]), None);
+}
+
+/// Bitwise reference implementation of a CRC update over a single byte.
+fn reference_crc(mut state: u128, byte: u128, polynomial: u128, width: u32) -> u128 {
+    state ^= byte << (width - 8);
+    for _ in 0..8 {
+        let carry = state & (1 << (width - 1)) != 0;
+        state = (state << 1) & ((1 << width) - 1);
+        if carry {
+            state ^= polynomial;
+        }
+    }
+    state
+}
+
+#[test]
+fn crc_update_of_constants() {
+    let mut context = new_context();
+    for (state, byte, polynomial, width) in
+        [(0_u128, 0x31_u128, 0x07_u128, 8), (0xf4, 0x32, 0x07, 8), (0xffff, 0x31, 0x1021, 16)]
+    {
+        let state_var = context.add_constant(FieldElement::from(state));
+        let byte_var = context.add_constant(FieldElement::from(byte));
+        let result = context.crc_update(state_var, byte_var, polynomial, width).unwrap();
+        let expected = reference_crc(state, byte, polynomial, width);
+        assert_eq!(context.constant(result), &FieldElement::from(expected));
+    }
+}
+
+#[test]
+fn crc_update_of_variables() {
+    for (polynomial, width) in [(0x07_u128, 8), (0x1021, 16)] {
+        let mut context = new_context();
+        let state = context.add_variable();
+        let byte = context.add_variable();
+        let result = context.crc_update(state, byte, polynomial, width).unwrap();
+
+        for (state_value, byte_value) in [(0_u128, 0x31_u128), (0xf4, 0x32), (0xff, 0xff)] {
+            let inputs = [(state, state_value), (byte, byte_value)];
+            let expected = reference_crc(state_value, byte_value, polynomial, width);
+            assert_eq!(execute(&mut context, &inputs, &[result]), Some(fields(&[expected])));
+        }
+    }
+}
+
+#[test]
+fn crc_update_rejects_invalid_polynomials() {
+    let mut context = new_context();
+    let state = context.add_variable();
+    let byte = context.add_variable();
+    assert!(context.crc_update(state, byte, 0x107, 8).is_err());
+    assert!(context.crc_update(state, byte, 0x07, 4).is_err());
+}
+
+#[test]
+fn assert_field_canonical_emits_no_opcodes() {
+    let mut context = new_context();
+    let var = context.add_variable();
+    let num_opcodes = context.acir_ir.opcodes().len();
+    context.assert_field_canonical(var).unwrap();
+    assert_eq!(context.acir_ir.opcodes().len(), num_opcodes);
+}
+
+#[test]
+fn conditional_truncate_with_constant_flag() {
+    let mut context = new_context();
+    let var = context.add_variable();
+
+    let zero = context.add_constant(FieldElement::zero());
+    let num_opcodes = context.acir_ir.opcodes().len();
+    let result = context.conditional_truncate(var, 8, 16, zero).unwrap();
+    assert_eq!(result, var);
+    assert_eq!(context.acir_ir.opcodes().len(), num_opcodes);
+
+    let one = context.add_constant(FieldElement::one());
+    let result = context.conditional_truncate(var, 8, 16, one).unwrap();
+    assert_eq!(execute(&mut context, &[(var, 0x1234)], &[result]), Some(fields(&[0x34])));
+}
+
+#[test]
+fn conditional_truncate_with_symbolic_flag() {
+    let mut context = new_context();
+    let var = context.add_variable();
+    let flag = context.add_variable();
+    let result = context.conditional_truncate(var, 8, 16, flag).unwrap();
+
+    let result_for = |context: &mut TestContext, flag_value| {
+        execute(context, &[(var, 0x1234), (flag, flag_value)], &[result])
+    };
+    assert_eq!(result_for(&mut context, 1), Some(fields(&[0x34])));
+    assert_eq!(result_for(&mut context, 0), Some(fields(&[0x1234])));
+}
+
+#[test]
+fn weighted_sum_skips_zero_weights() {
+    let mut context = new_context();
+    let vars = vecmap(0..3, |_| context.add_variable());
+    let weights = [FieldElement::zero(), FieldElement::one(), FieldElement::from(3_u128)];
+    let result = context.weighted_sum(&weights, &vars).unwrap();
+
+    let mut expected = Expression::default();
+    expected.push_addition_term(FieldElement::one(), context.var_to_witness(vars[1]).unwrap());
+    expected
+        .push_addition_term(FieldElement::from(3_u128), context.var_to_witness(vars[2]).unwrap());
+    assert_eq!(context.var_to_expression(result).unwrap(), expected);
+
+    let inputs = [(vars[0], 100), (vars[1], 5), (vars[2], 7)];
+    assert_eq!(execute(&mut context, &inputs, &[result]), Some(fields(&[26])));
+}
+
+#[test]
+fn weighted_sum_rejects_mismatched_lengths() {
+    let mut context = new_context();
+    let vars = vecmap(0..3, |_| context.add_variable());
+    assert!(context.weighted_sum(&[FieldElement::one()], &vars).is_err());
+}
+
+#[test]
+fn conditional_swap_with_symbolic_condition() {
+    let mut context = new_context();
+    let cond = context.add_variable();
+    let a = context.add_variable();
+    let b = context.add_variable();
+    let (first, second) = context.conditional_swap(cond, a, b).unwrap();
+
+    let inputs = |cond_value| [(cond, cond_value), (a, 3), (b, 7)];
+    assert_eq!(execute(&mut context, &inputs(0), &[first, second]), Some(fields(&[3, 7])));
+    assert_eq!(execute(&mut context, &inputs(1), &[first, second]), Some(fields(&[7, 3])));
+}
+
+#[test]
+fn compare_exchange_sorts_slots() {
+    let mut context = new_context();
+    let block_id = BlockId(0);
+    initialize_constant_array(&mut context, block_id, &[10, 40, 30, 20]);
+
+    let i = context.add_constant(1_u128);
+    let j = context.add_variable();
+    context.compare_exchange(block_id, &i, &j, 8).unwrap();
+    let outputs = read_array(&mut context, block_id, 4);
+
+    let result = execute(&mut context, &[(j, 3)], &outputs);
+    assert_eq!(result, Some(fields(&[10, 20, 30, 40])));
+    let result = execute(&mut context, &[(j, 0)], &outputs);
+    assert_eq!(result, Some(fields(&[40, 10, 30, 20])));
+}
+
+#[test]
+fn saturating_add_of_variables() {
+    let mut context = new_context();
+    let lhs = context.add_variable();
+    let rhs = context.add_variable();
+    let result = context.saturating_add(lhs, rhs, 8).unwrap();
+
+    for (lhs_value, rhs_value) in [(0_u128, 200_u128), (100, 155), (100, 156), (255, 255)] {
+        let result = execute(&mut context, &[(lhs, lhs_value), (rhs, rhs_value)], &[result]);
+        assert_eq!(result, Some(fields(&[(lhs_value + rhs_value).min(255)])));
+    }
+}
+
+#[test]
+fn saturating_accumulate_saturates_stored_value() {
+    let mut context = new_context();
+    let block_id = BlockId(0);
+    initialize_constant_array(&mut context, block_id, &[10, 250]);
+
+    let index = context.add_variable();
+    let delta = context.add_variable();
+    context.saturating_accumulate(block_id, &index, delta, 8).unwrap();
+    let outputs = read_array(&mut context, block_id, 2);
+
+    let result = execute(&mut context, &[(index, 1), (delta, 10)], &outputs);
+    assert_eq!(result, Some(fields(&[10, 255])));
+    let result = execute(&mut context, &[(index, 0), (delta, 10)], &outputs);
+    assert_eq!(result, Some(fields(&[20, 250])));
+}
+
+#[test]
+fn pow_var_of_constants() {
+    let mut context = new_context();
+    let base = context.add_constant(FieldElement::from(3_u128));
+    for exponent in [0, 1, 5, 8] {
+        let result = context.pow_var(base, exponent).unwrap();
+        assert_eq!(context.constant(result), &FieldElement::from(3_u128.pow(exponent)));
+    }
+}
+
+#[test]
+fn pow_var_uses_fewer_opcodes_than_repeated_multiplication() {
+    let mut context = new_context();
+    let x = context.add_variable();
+
+    let num_opcodes = context.acir_ir.opcodes().len();
+    let mut naive = x;
+    for _ in 1..8 {
+        naive = context.mul_var(naive, x).unwrap();
+    }
+    let naive = context.get_or_create_witness_var(naive).unwrap();
+    let naive_opcodes = context.acir_ir.opcodes().len() - num_opcodes;
+
+    let num_opcodes = context.acir_ir.opcodes().len();
+    let result = context.pow_var(x, 8).unwrap();
+    let result = context.get_or_create_witness_var(result).unwrap();
+    let pow_opcodes = context.acir_ir.opcodes().len() - num_opcodes;
+
+    assert!(pow_opcodes < naive_opcodes, "{pow_opcodes} >= {naive_opcodes}");
+    let result = execute(&mut context, &[(x, 3)], &[naive, result]);
+    assert_eq!(result, Some(fields(&[6561, 6561])));
+
+    assert_eq!(context.pow_var(x, 0).map(|one| context.is_constant_one(&one)), Ok(true));
+    assert_eq!(context.pow_var(x, 1), Ok(x));
+}
+
+#[test]
+fn select_var_with_constant_condition() {
+    let mut context = new_context();
+    let then_var = context.add_variable();
+    let else_var = context.add_variable();
+    let num_opcodes = context.acir_ir.opcodes().len();
+
+    let one = context.add_constant(FieldElement::one());
+    assert_eq!(context.select_var(one, then_var, else_var).unwrap(), then_var);
+    let zero = context.add_constant(FieldElement::zero());
+    assert_eq!(context.select_var(zero, then_var, else_var).unwrap(), else_var);
+    assert_eq!(context.acir_ir.opcodes().len(), num_opcodes);
+}
+
+#[test]
+fn select_var_with_equal_branches() {
+    let mut context = new_context();
+    let cond = context.add_variable();
+    let x = context.add_variable();
+    let two = context.add_constant(FieldElement::from(2_u128));
+    // Two distinct variables which resolve to the same expression `2 * x`
+    let then_var = context.mul_var(x, two).unwrap();
+    let else_var = context.mul_var(two, x).unwrap();
+    assert_ne!(then_var, else_var);
+
+    let result = context.select_var(cond, then_var, else_var).unwrap();
+    assert_eq!(result, else_var);
+}
+
+#[test]
+fn select_var_with_symbolic_condition() {
+    let mut context = new_context();
+    let cond = context.add_variable();
+    let then_var = context.add_variable();
+    let else_var = context.add_variable();
+    let result = context.select_var(cond, then_var, else_var).unwrap();
+
+    let inputs = |cond_value| [(cond, cond_value), (then_var, 3), (else_var, 7)];
+    assert_eq!(execute(&mut context, &inputs(1), &[result]), Some(fields(&[3])));
+    assert_eq!(execute(&mut context, &inputs(0), &[result]), Some(fields(&[7])));
+}
+
+/// Reverses the order of the `bit_size` least significant bits of `value` one bit at a time.
+fn reverse_bits(value: u128, bit_size: u32) -> u128 {
+    (0..bit_size).fold(0, |reversed, i| (reversed << 1) | ((value >> i) & 1))
+}
+
+#[test]
+fn bit_reverse_of_constants() {
+    let mut context = new_context();
+    for (value, bit_size) in [(0b1101_0001_u128, 8), (1, 8), (0xff, 8), (0x1234, 16), (1, 16)] {
+        let var = context.add_constant(FieldElement::from(value));
+        let result = context.bit_reverse(var, bit_size).unwrap();
+        let expected = reverse_bits(value, bit_size);
+        assert_eq!(context.constant(result), &FieldElement::from(expected));
+    }
+}
+
+#[test]
+fn bit_reverse_of_variables() {
+    for bit_size in [8, 16] {
+        let mut context = new_context();
+        let var = context.add_variable();
+        let result = context.bit_reverse(var, bit_size).unwrap();
+
+        for value in [0b1101_0001_u128, 1, 0xff] {
+            let expected = reverse_bits(value, bit_size);
+            assert_eq!(
+                execute(&mut context, &[(var, value)], &[result]),
+                Some(fields(&[expected]))
+            );
+        }
+    }
+}
+
+#[test]
+fn assert_prefix_eq_compares_prefix() {
+    let mut context = new_context();
+    let (a, b) = (BlockId(0), BlockId(1));
+    initialize_constant_array(&mut context, a, &[10, 20, 30]);
+    initialize_constant_array(&mut context, b, &[10, 20, 99]);
+
+    let predicate = context.add_variable();
+    context.assert_prefix_eq(a, b, 2, predicate).unwrap();
+    assert_eq!(execute(&mut context, &[(predicate, 1)], &[]), Some(vec![]));
+
+    context.assert_prefix_eq(a, b, 3, predicate).unwrap();
+    assert_eq!(execute(&mut context, &[(predicate, 1)], &[]), None);
+    assert_eq!(execute(&mut context, &[(predicate, 0)], &[]), Some(vec![]));
+}
+
+#[test]
+fn assert_prefix_eq_with_empty_prefix() {
+    let mut context = new_context();
+    let one = context.add_constant(FieldElement::one());
+    let num_opcodes = context.acir_ir.opcodes().len();
+    context.assert_prefix_eq(BlockId(0), BlockId(1), 0, one).unwrap();
+    assert_eq!(context.acir_ir.opcodes().len(), num_opcodes);
+}
+
+#[test]
+fn rotate_var_of_constants() {
+    let mut context = new_context();
+    let var = context.add_constant(FieldElement::from(0x8000_0001_u128));
+
+    let result = context.rotate_left_var(var, 7, 32).unwrap();
+    assert_eq!(context.constant(result), &FieldElement::from(0x8000_0001_u32.rotate_left(7)));
+    let result = context.rotate_right_var(var, 7, 32).unwrap();
+    assert_eq!(context.constant(result), &FieldElement::from(0x8000_0001_u32.rotate_right(7)));
+}
+
+#[test]
+fn rotate_var_by_multiple_of_bit_size_is_noop() {
+    let mut context = new_context();
+    let var = context.add_variable();
+    let num_opcodes = context.acir_ir.opcodes().len();
+
+    assert_eq!(context.rotate_left_var(var, 0, 32).unwrap(), var);
+    assert_eq!(context.rotate_left_var(var, 32, 32).unwrap(), var);
+    assert_eq!(context.rotate_right_var(var, 64, 32).unwrap(), var);
+    assert_eq!(context.acir_ir.opcodes().len(), num_opcodes);
+}
+
+#[test]
+fn rotate_var_of_variables() {
+    let mut context = new_context();
+    let var = context.add_variable();
+    let left = context.rotate_left_var(var, 7, 32).unwrap();
+    let right = context.rotate_right_var(var, 39, 32).unwrap();
+
+    for value in [0x8000_0001_u32, 0x1234_5678, u32::MAX, 0] {
+        let result = execute(&mut context, &[(var, value.into())], &[left, right]);
+        let expected = [value.rotate_left(7), value.rotate_right(7)].map(u128::from);
+        assert_eq!(result, Some(fields(&expected)));
+    }
+}
+
+#[test]
+fn conditional_set_bits_of_constants() {
+    let mut context = new_context();
+    let x = context.add_constant(FieldElement::from(0b1010_0101_u128));
+    let mask = context.add_constant(FieldElement::from(0b0011_1100_u128));
+    let num_opcodes = context.acir_ir.opcodes().len();
+
+    let one = context.add_constant(FieldElement::one());
+    let result = context.conditional_set_bits(x, mask, one, AcirType::unsigned(8)).unwrap();
+    assert_eq!(context.constant(result), &FieldElement::from(0b1011_1101_u128));
+    let zero = context.add_constant(FieldElement::zero());
+    let result = context.conditional_set_bits(x, mask, zero, AcirType::unsigned(8)).unwrap();
+    assert_eq!(context.constant(result), &FieldElement::from(0b1000_0001_u128));
+    assert_eq!(context.acir_ir.opcodes().len(), num_opcodes);
+}
+
+#[test]
+fn conditional_set_bits_with_constant_mask() {
+    let mut context = new_context();
+    let x = context.add_variable();
+    let set = context.add_variable();
+    let mask = context.add_constant(FieldElement::from(0b0011_1100_u128));
+    let result = context.conditional_set_bits(x, mask, set, AcirType::unsigned(8)).unwrap();
+
+    for value in [0_u8, 0b1010_0101, 0b0101_1010, u8::MAX] {
+        let set_bits = execute(&mut context, &[(x, value.into()), (set, 1)], &[result]);
+        assert_eq!(set_bits, Some(fields(&[u128::from(value | 0b0011_1100)])), "set({value})");
+        let cleared_bits = execute(&mut context, &[(x, value.into()), (set, 0)], &[result]);
+        assert_eq!(
+            cleared_bits,
+            Some(fields(&[u128::from(value & 0b1100_0011)])),
+            "clear({value})"
+        );
+    }
+}
+
+#[test]
+fn matrix_vector_mul_of_2x2_matrix() {
+    let mut context = new_context();
+    let (matrix, vector, out_block) = (BlockId(0), BlockId(1), BlockId(2));
+    initialize_constant_array(&mut context, matrix, &[1, 2, 3, 4]);
+    initialize_constant_array(&mut context, vector, &[5, 6]);
+    initialize_constant_array(&mut context, out_block, &[0, 0]);
+    let num_opcodes = context.acir_ir.opcodes().len();
+
+    context.matrix_vector_mul(matrix, vector, 2, 2, out_block).unwrap();
+
+    // The vector is read once, each matrix entry once, and each output slot is written once
+    assert_eq!(count_memory_ops(&context, num_opcodes), 8);
+    let outputs = read_array(&mut context, out_block, 2);
+    assert_eq!(execute(&mut context, &[], &outputs), Some(fields(&[17, 39])));
+}
+
+#[test]
+fn opcode_count_by_kind() {
+    let mut context = new_context();
+    let block_id = BlockId(0);
+    initialize_constant_array(&mut context, block_id, &[1, 2]);
+
+    let var = context.add_variable();
+    let position = context.add_constant(FieldElement::one());
+    let value = context.read_from_memory(block_id, &position).unwrap();
+    let product = context.mul_var(var, value).unwrap();
+    context.xor_var(product, var, AcirType::unsigned(8)).unwrap();
+
+    let counts = context.opcode_count_by_kind();
+    assert_eq!(counts.get("MemoryInit"), Some(&1));
+    assert_eq!(counts.get("MemoryOp"), Some(&1));
+    // Two to assign the constants initializing the memory to witnesses, and one for the product
+    assert_eq!(counts.get("AssertZero"), Some(&3));
+    assert_eq!(counts.get("BlackBox"), Some(&1));
+    assert_eq!(counts.values().sum::<usize>(), context.opcode_count());
+    // The two constants, `var`, the value read from memory, the product and the XOR output
+    assert_eq!(context.witness_count(), 6);
+}
+
+#[test]
+fn read_from_memory_with_predicate() {
+    let mut context = new_context();
+    let block_id = BlockId(0);
+    initialize_constant_array(&mut context, block_id, &[10, 20]);
+
+    let predicate = context.add_variable();
+    let index = context.add_constant(FieldElement::one());
+    let value = context.read_from_memory_with_predicate(block_id, &index, predicate).unwrap();
+
+    let Some(Opcode::MemoryOp { predicate: Some(expr), .. }) = context.acir_ir.opcodes().last()
+    else {
+        panic!("expected a predicated memory operation");
+    };
+    assert_eq!(expr, &context.var_to_expression(predicate).unwrap());
+    assert_eq!(execute(&mut context, &[(predicate, 1)], &[value]), Some(fields(&[20])));
+}
+
+#[test]
+fn write_to_memory_with_predicate() {
+    let mut context = new_context();
+    let block_id = BlockId(0);
+    initialize_constant_array(&mut context, block_id, &[10, 20]);
+
+    let predicate = context.add_variable();
+    let index = context.add_constant(FieldElement::zero());
+    let value = context.add_constant(FieldElement::from(30_u128));
+    context.write_to_memory_with_predicate(block_id, &index, &value, predicate).unwrap();
+
+    let Some(Opcode::MemoryOp { predicate: Some(expr), .. }) = context.acir_ir.opcodes().last()
+    else {
+        panic!("expected a predicated memory operation");
+    };
+    assert_eq!(expr, &context.var_to_expression(predicate).unwrap());
+}
+
+#[test]
+fn memory_operations_with_constant_one_predicate_are_unpredicated() {
+    let mut context = new_context();
+    let block_id = BlockId(0);
+    initialize_constant_array(&mut context, block_id, &[10, 20]);
+
+    let index = context.add_constant(FieldElement::one());
+    context.read_from_memory(block_id, &index).unwrap();
+    assert!(matches!(
+        context.acir_ir.opcodes().last(),
+        Some(Opcode::MemoryOp { predicate: None, .. })
+    ));
+}
+
+#[test]
+fn assert_is_bool_of_non_boolean_constant_warns() {
+    let mut context = new_context();
+    let one = context.add_constant(FieldElement::one());
+    context.assert_is_bool(one).unwrap();
+    assert!(context.warnings.is_empty());
+
+    let two = context.add_constant(FieldElement::from(2_u128));
+    context.assert_is_bool(two).unwrap();
+    assert_eq!(context.warnings.len(), 1);
+}
+
+#[test]
+fn assert_is_bool_is_emitted_once_per_variable() {
+    let mut context = new_context();
+    let var = context.add_variable();
+
+    context.assert_is_bool(var).unwrap();
+    let num_opcodes = context.acir_ir.opcodes().len();
+    context.assert_is_bool(var).unwrap();
+    assert_eq!(context.acir_ir.opcodes().len(), num_opcodes);
+
+    assert_eq!(execute(&mut context, &[(var, 1)], &[]), Some(vec![]));
+    assert_eq!(execute(&mut context, &[(var, 2)], &[]), None);
+}
+
+#[test]
+fn priority_encode_of_variables() {
+    let mut context = new_context();
+    let bits = vecmap(0..4, |_| context.add_variable());
+    let (index, valid) = context.priority_encode(&bits).unwrap();
+
+    let inputs =
+        |values: [u128; 4]| -> Vec<(AcirVar, u128)> { bits.iter().copied().zip(values).collect() };
+    assert_eq!(
+        execute(&mut context, &inputs([0, 1, 1, 0]), &[index, valid]),
+        Some(fields(&[2, 1]))
+    );
+    assert_eq!(
+        execute(&mut context, &inputs([1, 0, 0, 1]), &[index, valid]),
+        Some(fields(&[3, 1]))
+    );
+    assert_eq!(
+        execute(&mut context, &inputs([0, 0, 0, 0]), &[index, valid]),
+        Some(fields(&[0, 0]))
+    );
+}
+
+#[test]
+fn priority_encode_of_constants() {
+    let mut context = new_context();
+    let bits = vecmap([1_u128, 1, 0], |bit| context.add_constant(FieldElement::from(bit)));
+    let (index, valid) = context.priority_encode(&bits).unwrap();
+    assert_eq!(context.constant(index), &FieldElement::one());
+    assert_eq!(context.constant(valid), &FieldElement::one());
+
+    let (index, valid) = context.priority_encode(&[]).unwrap();
+    assert_eq!(context.constant(index), &FieldElement::zero());
+    assert_eq!(context.constant(valid), &FieldElement::zero());
+}
+
+#[test]
+fn more_than_eq_signed_across_sign_boundary() {
+    let mut context = new_context();
+    let lhs = context.add_variable();
+    let rhs = context.add_variable();
+    let result = context.more_than_eq_signed(lhs, rhs, 8).unwrap();
+
+    // 8-bit two's complement: -1 is 255, -2 is 254 and -128 is 128
+    for (a, b, expected) in [
+        (255, 254, 1),
+        (254, 255, 0),
+        (255, 0, 0),
+        (0, 255, 1),
+        (255, 255, 1),
+        (0, 0, 1),
+        (127, 128, 1),
+        (128, 127, 0),
+    ] {
+        assert_eq!(
+            execute(&mut context, &[(lhs, a), (rhs, b)], &[result]),
+            Some(fields(&[expected])),
+            "{a} >= {b}"
+        );
+    }
+}
+
+#[test]
+fn clamp_index_of_constants() {
+    let mut context = new_context();
+    for (index, len, expected) in [(2_u128, 4, 2_u128), (7, 4, 3), (3, 4, 3), (5, 0, 0)] {
+        let index = context.add_constant(FieldElement::from(index));
+        let clamped = context.clamp_index(index, len, 8).unwrap();
+        assert_eq!(context.constant(clamped), &FieldElement::from(expected));
+    }
+}
+
+#[test]
+fn clamp_index_of_variable() {
+    let mut context = new_context();
+    let index = context.add_variable();
+    let clamped = context.clamp_index(index, 4, 8).unwrap();
+
+    assert_eq!(execute(&mut context, &[(index, 1)], &[clamped]), Some(fields(&[1])));
+    assert_eq!(execute(&mut context, &[(index, 200)], &[clamped]), Some(fields(&[3])));
+}
+
+#[test]
+fn mul_var_reuses_product_of_witnesses() {
+    let mut context = new_context();
+    let a = context.add_variable();
+    let b = context.add_variable();
+
+    let product = context.mul_var(a, b).unwrap();
+    let num_vars = context.vars.len();
+    assert_eq!(context.mul_var(a, b).unwrap(), product);
+    assert_eq!(context.mul_var(b, a).unwrap(), product);
+    assert_eq!(context.vars.len(), num_vars);
+
+    // Products involving constants are not memoized
+    let two = context.add_constant(FieldElement::from(2_u128));
+    let doubled = context.mul_var(a, two).unwrap();
+    assert_ne!(context.mul_var(a, two).unwrap(), doubled);
+}
+
+#[test]
+fn euclidean_division_of_wide_integers() {
+    let mut context = new_context();
+    let lhs = context.add_variable();
+    let rhs = context.add_variable();
+    let one = context.add_constant(FieldElement::one());
+    let (quotient, remainder) = context.euclidean_division_var(lhs, rhs, 130, one).unwrap();
+
+    let big = (1_u128 << 127) + 5;
+    assert_eq!(
+        execute(&mut context, &[(lhs, 1000), (rhs, 7)], &[quotient, remainder]),
+        Some(fields(&[142, 6]))
+    );
+    assert_eq!(
+        execute(&mut context, &[(lhs, big), (rhs, 3)], &[quotient, remainder]),
+        Some(fields(&[big / 3, big % 3]))
+    );
+}
+
+#[test]
+fn euclidean_division_of_too_wide_integers_is_an_error() {
+    let mut context = new_context();
+    let lhs = context.add_variable();
+    let rhs = context.add_variable();
+    let one = context.add_constant(FieldElement::one());
+    assert!(matches!(
+        context.euclidean_division_var(lhs, rhs, 200, one),
+        Err(RuntimeError::UnsupportedDivisionBitSize { num_bits: 200, .. })
+    ));
+}
+
+#[test]
+fn would_fit_in_one_identity_depends_on_order() {
+    let mut context = new_context();
+    context.set_expression_width(ExpressionWidth::Bounded { width: 2 });
+    let (a, b, c) = (context.add_variable(), context.add_variable(), context.add_variable());
+    let zero = context.add_constant(FieldElement::zero());
+    let a_plus_b = context.add_var(a, b).unwrap();
+    let minus_c = context.sub_var(zero, c).unwrap();
+
+    // `(a + b) + c` needs three witnesses so would be split...
+    assert!(!context.would_fit_in_one_identity(a_plus_b, c).unwrap());
+
+    // ...but `(a + b) + (c - c)` fits as `c` cancels out
+    assert!(context.would_fit_in_one_identity(c, minus_c).unwrap());
+    let cancelled = context.add_var(c, minus_c).unwrap();
+    assert!(context.would_fit_in_one_identity(a_plus_b, cancelled).unwrap());
+
+    context.set_expression_width(ExpressionWidth::Unbounded);
+    assert_eq!(context.expression_width(), ExpressionWidth::Unbounded);
+    assert!(context.would_fit_in_one_identity(a_plus_b, c).unwrap());
+}
+
+#[test]
+fn opcode_call_stacks_distinguish_call_stacks() {
+    let mut context = new_context();
+    let (a, b) = (context.add_variable(), context.add_variable());
+    let zero = context.add_constant(FieldElement::zero());
+
+    context.set_call_stack(vec![Location::dummy()]);
+    context.assert_eq_var(a, zero, None).unwrap();
+    context.set_call_stack(vec![Location::dummy(), Location::dummy()]);
+    context.assert_eq_var(b, zero, None).unwrap();
+
+    let call_stacks = context.acir_ir.opcode_call_stacks();
+    assert_eq!(call_stacks.len(), 2);
+    assert_eq!(call_stacks[0].0, OpcodeLocation::Acir(0));
+    assert_eq!(call_stacks[1].0, OpcodeLocation::Acir(1));
+    assert_ne!(call_stacks[0].1, call_stacks[1].1);
+    assert_eq!(context.acir_ir.call_stacks.get_call_stack(call_stacks[1].1).len(), 2);
+}
+
+#[test]
+fn clz_var_of_constants() {
+    let mut context = new_context();
+    for (value, expected) in [(0_u128, 8_u128), (1, 7), (16, 3), (255, 0)] {
+        let var = context.add_constant(FieldElement::from(value));
+        let clz = context.clz_var(var, 8).unwrap();
+        assert_eq!(context.constant(clz), &FieldElement::from(expected));
+    }
+}
+
+#[test]
+fn clz_var_of_variable() {
+    let mut context = new_context();
+    let var = context.add_variable();
+    let clz = context.clz_var(var, 8).unwrap();
+
+    assert_eq!(execute(&mut context, &[(var, 0)], &[clz]), Some(fields(&[8])));
+    assert_eq!(execute(&mut context, &[(var, 16)], &[clz]), Some(fields(&[3])));
+    assert_eq!(execute(&mut context, &[(var, 1)], &[clz]), Some(fields(&[7])));
+    assert_eq!(execute(&mut context, &[(var, 255)], &[clz]), Some(fields(&[0])));
+}
+
+#[test]
+fn byte_decompose_round_trip() {
+    let mut context = new_context();
+    let var = context.add_variable();
+    let little = context.byte_decompose(Endian::Little, var, 4, AcirType::unsigned(8)).unwrap();
+    let big = context.byte_decompose(Endian::Big, var, 4, AcirType::unsigned(8)).unwrap();
+    let little = vecmap(little.flatten(), |(byte, _)| byte);
+    let big = vecmap(big.flatten(), |(byte, _)| byte);
+    let recomposed = context.bytes_to_field(&little).unwrap();
+
+    let mut outputs = little.clone();
+    outputs.extend(big);
+    outputs.push(recomposed);
+    assert_eq!(
+        execute(&mut context, &[(var, 0x12345678)], &outputs),
+        Some(fields(&[0x78, 0x56, 0x34, 0x12, 0x12, 0x34, 0x56, 0x78, 0x12345678]))
+    );
+}
+
+#[test]
+fn initialize_array_reuses_constant_witnesses() {
+    let mut context = new_context();
+    let num_vars = context.vars.len();
+    initialize_constant_array(&mut context, BlockId(0), &[5, 5, 7, 5]);
+    initialize_constant_array(&mut context, BlockId(1), &[7, 5]);
+
+    // One witness for each distinct constant, shared between both arrays
+    assert_eq!(context.witness_count(), 2);
+    let inits: Vec<_> = context
+        .acir_ir
+        .opcodes()
+        .iter()
+        .filter_map(|opcode| match opcode {
+            Opcode::MemoryInit { init, .. } => Some(init.clone()),
+            _ => None,
+        })
+        .collect();
+    assert_eq!(inits[0], vec![inits[0][0], inits[0][0], inits[1][0], inits[0][0]]);
+    assert_eq!(inits[1], vec![inits[0][2], inits[0][0]]);
+    // Only the constants themselves were added as variables
+    assert_eq!(context.vars.len(), num_vars + 6);
+}
+
+#[test]
+fn range_constrain_var_skips_identical_range_checks() {
+    let mut context = new_context();
+    let one = context.add_constant(FieldElement::one());
+    let var = context.add_variable();
+    // An equivalent variable with its own id shares the witness of `var`
+    let zero = context.add_constant(FieldElement::zero());
+    let same_var = context.add_var(var, zero).unwrap();
+    assert_ne!(var, same_var);
+
+    context.range_constrain_var(var, &NumericType::unsigned(8), None, one).unwrap();
+    let num_opcodes = context.acir_ir.opcodes().len();
+    context.range_constrain_var(var, &NumericType::unsigned(8), None, one).unwrap();
+    context.range_constrain_var(same_var, &NumericType::unsigned(8), None, one).unwrap();
+    assert_eq!(context.acir_ir.opcodes().len(), num_opcodes);
+}
+
+#[test]
+fn range_constrain_var_skips_looser_range_checks() {
+    let mut context = new_context();
+    let one = context.add_constant(FieldElement::one());
+    let var = context.add_variable();
+
+    context.range_constrain_var(var, &NumericType::unsigned(8), None, one).unwrap();
+    let num_opcodes = context.acir_ir.opcodes().len();
+    context.range_constrain_var(var, &NumericType::unsigned(16), None, one).unwrap();
+    context.range_constrain_var(var, &NumericType::unsigned(8), None, one).unwrap();
+    assert_eq!(context.acir_ir.opcodes().len(), num_opcodes);
+
+    // A tighter range check is still emitted
+    context.range_constrain_var(var, &NumericType::unsigned(4), None, one).unwrap();
+    assert_eq!(context.acir_ir.opcodes().len(), num_opcodes + 1);
+    assert_eq!(execute(&mut context, &[(var, 15)], &[var]), Some(fields(&[15])));
+    assert_eq!(execute(&mut context, &[(var, 16)], &[var]), None);
+}
+
+#[test]
+fn range_constrain_var_keeps_range_checks_with_new_messages() {
+    let mut context = new_context();
+    let one = context.add_constant(FieldElement::one());
+    let var = context.add_variable();
+    let message = Some("out of range".to_string());
+
+    context.range_constrain_var(var, &NumericType::unsigned(8), None, one).unwrap();
+    let num_opcodes = context.acir_ir.opcodes().len();
+    context.range_constrain_var(var, &NumericType::unsigned(8), message.clone(), one).unwrap();
+    assert_eq!(context.acir_ir.opcodes().len(), num_opcodes + 1);
+    assert_eq!(context.acir_ir.assertion_payloads.len(), 1);
+
+    // Range checks with the same message, or without one, can rely on the check above
+    context.range_constrain_var(var, &NumericType::unsigned(16), message, one).unwrap();
+    context.range_constrain_var(var, &NumericType::unsigned(8), None, one).unwrap();
+    assert_eq!(context.acir_ir.opcodes().len(), num_opcodes + 1);
+}
+
+#[test]
+fn shift_right_var_by_constant() {
+    let mut context = new_context();
+    let one = context.add_constant(FieldElement::one());
+    let lhs = context.add_variable();
+    let outputs = vecmap([0_u128, 3, 8, 9], |shift| {
+        let rhs = context.add_constant(FieldElement::from(shift));
+        context.shift_right_var(lhs, rhs, 8, one).unwrap()
+    });
+    assert_eq!(outputs[0], lhs);
+    assert_eq!(context.constant(outputs[2]), &FieldElement::zero());
+    assert_eq!(context.constant(outputs[3]), &FieldElement::zero());
+
+    assert_eq!(
+        execute(&mut context, &[(lhs, 0b1011_0110)], &outputs),
+        Some(fields(&[0b1011_0110, 0b1_0110, 0, 0]))
+    );
+}
+
+#[test]
+fn shift_right_var_by_variable() {
+    let mut context = new_context();
+    let one = context.add_constant(FieldElement::one());
+    let lhs = context.add_variable();
+    let rhs = context.add_variable();
+    let result = context.shift_right_var(lhs, rhs, 8, one).unwrap();
+
+    for (shift, expected) in [(0, 0b1011_0110), (3, 0b1_0110), (7, 1), (8, 0), (200, 0)] {
+        assert_eq!(
+            execute(&mut context, &[(lhs, 0b1011_0110), (rhs, shift)], &[result]),
+            Some(fields(&[expected])),
+            "shift by {shift}"
+        );
+    }
+}
+
+#[test]
+fn gcd_var_of_constants() {
+    let mut context = new_context();
+    for (a, b) in [(35_u128, 64_u128), (84, 36), (0, 9), (0, 0)] {
+        let a_var = context.add_constant(FieldElement::from(a));
+        let b_var = context.add_constant(FieldElement::from(b));
+        let result = context.gcd_var(a_var, b_var, 16).unwrap();
+        assert_eq!(context.constant(result), &FieldElement::from(a.gcd(&b)));
+    }
+}
+
+#[test]
+fn gcd_var_of_coprime_and_shared_factor_inputs() {
+    let mut context = new_context();
+    let a = context.add_variable();
+    let b = context.add_variable();
+    let result = context.gcd_var(a, b, 16).unwrap();
+
+    assert_eq!(execute(&mut context, &[(a, 35), (b, 64)], &[result]), Some(fields(&[1])));
+    assert_eq!(execute(&mut context, &[(a, 84), (b, 36)], &[result]), Some(fields(&[12])));
+}
+
+#[test]
+fn bitwise_ops_fold_constant_operands() {
+    let mut context = new_context();
+    let typ = AcirType::unsigned(8);
+    let lhs = context.add_constant(FieldElement::from(0b1100_1010_u128));
+    let rhs = context.add_constant(FieldElement::from(0b0101_0110_u128));
+
+    let xor = context.xor_var(lhs, rhs, typ.clone()).unwrap();
+    let and = context.and_var(lhs, rhs, typ.clone()).unwrap();
+    let or = context.or_var(lhs, rhs, typ).unwrap();
+    assert_eq!(context.constant(xor), &FieldElement::from(0b1001_1100_u128));
+    assert_eq!(context.constant(and), &FieldElement::from(0b0100_0010_u128));
+    assert_eq!(context.constant(or), &FieldElement::from(0b1101_1110_u128));
+    assert!(
+        !context
+            .acir_ir
+            .opcodes()
+            .iter()
+            .any(|opcode| matches!(opcode, Opcode::BlackBoxFuncCall(_)))
+    );
+}
+
+#[test]
+fn division_by_power_of_two_does_not_use_brillig() {
+    let mut context = new_context();
+    let one = context.add_constant(FieldElement::one());
+    let sixteen = context.add_constant(FieldElement::from(16_u128));
+    let lhs = context.add_variable();
+    let predicate = context.add_variable();
+    let typ = AcirType::unsigned(32);
+    let quotient = context.div_var(lhs, sixteen, typ.clone(), one).unwrap();
+    let remainder = context.modulo_var(lhs, sixteen, typ.clone(), 32, one).unwrap();
+    let predicated_quotient = context.div_var(lhs, sixteen, typ.clone(), predicate).unwrap();
+    let predicated_remainder = context.modulo_var(lhs, sixteen, typ, 32, predicate).unwrap();
+    assert!(
+        !context
+            .acir_ir
+            .opcodes()
+            .iter()
+            .any(|opcode| matches!(opcode, Opcode::BrilligCall { .. }))
+    );
+
+    let outputs = [quotient, remainder, predicated_quotient, predicated_remainder];
+    let value = 0xdead_beef;
+    assert_eq!(
+        execute(&mut context, &[(lhs, value), (predicate, 1)], &outputs),
+        Some(fields(&[value / 16, value % 16, value / 16, value % 16]))
+    );
+    assert_eq!(
+        execute(&mut context, &[(lhs, value), (predicate, 0)], &outputs),
+        Some(fields(&[value / 16, value % 16, 0, 0]))
+    );
+}
+
+#[test]
+fn division_by_power_of_two_range_constrains_remainder() {
+    let mut context = new_context();
+    let one = context.add_constant(FieldElement::one());
+    let sixteen = context.add_constant(FieldElement::from(16_u128));
+    let lhs = context.add_variable();
+    let typ = AcirType::unsigned(32);
+    let remainder = context.modulo_var(lhs, sixteen, typ, 32, one).unwrap();
+    let remainder_witness = context.var_to_witness(remainder).unwrap();
+
+    assert!(context.acir_ir.opcodes().iter().any(|opcode| matches!(
+        opcode,
+        Opcode::BlackBoxFuncCall(BlackBoxFuncCall::RANGE { input })
+            if input.to_witness() == remainder_witness && input.num_bits() == 4
+    )));
+}
+
+#[test]
+fn division_under_false_predicate() {
+    let mut context = new_context();
+    let lhs = context.add_variable();
+    let predicate = context.add_variable();
+    let typ = AcirType::unsigned(32);
+    let mut outputs = Vec::new();
+    for rhs in [0_u128, 6] {
+        let rhs = context.add_constant(FieldElement::from(rhs));
+        outputs.push(context.div_var(lhs, rhs, typ.clone(), predicate).unwrap());
+        outputs.push(context.modulo_var(lhs, rhs, typ.clone(), 32, predicate).unwrap());
+    }
+
+    assert_eq!(
+        execute(&mut context, &[(lhs, 100), (predicate, 0)], &outputs),
+        Some(fields(&[0, 0, 0, 0]))
+    );
+    // Dividing by zero fails once the predicate is enabled
+    assert_eq!(execute(&mut context, &[(lhs, 100), (predicate, 1)], &outputs), None);
+}
+
+#[test]
+fn find_first_set_of_constants() {
+    let mut context = new_context();
+    for value in [0_u32, 1, 2, 12, 0x80, 0x8000_0000, u32::MAX] {
+        let var = context.add_constant(FieldElement::from(u128::from(value)));
+        let index = context.find_first_set(var, 32).unwrap();
+        assert_eq!(
+            context.constant(index),
+            &FieldElement::from(u128::from(value.trailing_zeros())),
+            "find_first_set({value})"
+        );
+    }
+}
+
+#[test]
+fn find_first_set_of_variable() {
+    let mut context = new_context();
+    let var = context.add_variable();
+    let index = context.find_first_set(var, 32).unwrap();
+
+    for value in [0_u32, 1, 2, 12, 0x80, 0x8000_0000, u32::MAX] {
+        assert_eq!(
+            execute(&mut context, &[(var, u128::from(value))], &[index]),
+            Some(fields(&[u128::from(value.trailing_zeros())])),
+            "find_first_set({value})"
+        );
+    }
+}
+
+#[test]
+fn batch_inv_var_matches_inv_var() {
+    let mut context = new_context();
+    let one = context.add_constant(FieldElement::one());
+    let vars = [
+        context.add_variable(),
+        context.add_constant(FieldElement::from(11_u128)),
+        context.add_variable(),
+        context.add_variable(),
+    ];
+    let num_opcodes = context.acir_ir.opcodes().len();
+    let inverses = context.batch_inv_var(&vars, one).unwrap();
+    let num_brillig_calls = context.acir_ir.opcodes()[num_opcodes..]
+        .iter()
+        .filter(|opcode| matches!(opcode, Opcode::BrilligCall { .. }))
+        .count();
+    assert_eq!(num_brillig_calls, 1);
+    let expected = vecmap(vars, |var| context.inv_var(var, one).unwrap());
+
+    let inputs = [(vars[0], 3), (vars[2], 5), (vars[3], 7)];
+    let outputs = execute(&mut context, &inputs, &inverses).unwrap();
+    assert_eq!(outputs[1], FieldElement::from(11_u128).inverse());
+    assert_eq!(Some(outputs), execute(&mut context, &inputs, &expected));
+    assert_eq!(execute(&mut context, &[(vars[0], 3), (vars[2], 0), (vars[3], 7)], &inverses), None);
+}
+
+#[test]
+fn batch_inv_var_under_false_predicate() {
+    let mut context = new_context();
+    let predicate = context.add_variable();
+    let vars = [context.add_variable(), context.add_variable()];
+    let inverses = context.batch_inv_var(&vars, predicate).unwrap();
+
+    let inputs = [(predicate, 0), (vars[0], 0), (vars[1], 5)];
+    assert!(execute(&mut context, &inputs, &inverses).is_some());
+    let inputs = [(predicate, 1), (vars[0], 0), (vars[1], 5)];
+    assert_eq!(execute(&mut context, &inputs, &inverses), None);
+}
@@ -166,7 +166,7 @@ impl AcirValue {
 }
 
 /// A Reference to an `AcirVarData`
-#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
 pub(super) struct AcirVar(usize);
 
 impl AcirVar {
@@ -27,6 +27,7 @@ use noirc_errors::call_stack::{CallStackHelper, CallStackId};
 use num_bigint::BigUint;
 use std::collections::BTreeSet;
 use std::sync::Arc;
+use tracing::trace;
 
 use super::brillig_black_box::convert_black_box_call;
 use super::brillig_block_variables::{BlockVariables, allocate_value_with_type};
@@ -86,7 +87,7 @@ impl<'block, Registers: RegisterAllocator> BrilligBlock<'block, Registers> {
             }
         }
 
-        let variables = BlockVariables::new(live_in_no_globals);
+        let variables = BlockVariables::new(live_in_no_globals, function_context);
 
         brillig_context.set_allocated_registers(
             variables
@@ -209,6 +210,13 @@ impl<'block, Registers: RegisterAllocator> BrilligBlock<'block, Registers> {
         }
 
         self.convert_ssa_terminator(terminator_instruction, dfg);
+
+        trace!(
+            "defined {} variables in block {} of function {}",
+            self.variables.total_allocated(),
+            self.block_id,
+            self.function_context.function_id()
+        );
     }
 
     /// Creates a unique global label for a block.
@@ -43,12 +43,19 @@ use super::brillig_fn::FunctionContext;
 #[derive(Debug, Default)]
 pub(crate) struct BlockVariables {
     available_variables: HashSet<ValueId>,
+    /// The number of variables defined in this block so far, including those which have since been removed.
+    total_allocated: usize,
 }
 
 impl BlockVariables {
     /// Creates a BlockVariables instance. It uses the variables that are live in to the block and the global available variables (block parameters)
-    pub(crate) fn new(live_in: HashSet<ValueId>) -> Self {
-        BlockVariables { available_variables: live_in }
+    pub(crate) fn new(
+        live_in: impl IntoIterator<Item = ValueId>,
+        function_context: &FunctionContext,
+    ) -> Self {
+        let mut variables = BlockVariables::default();
+        variables.extend_available(live_in, function_context);
+        variables
     }
 
     /// Returns all variables that have not been removed at this point.
@@ -81,8 +88,10 @@ impl BlockVariables {
         if function_context.ssa_value_allocations.insert(value_id, variable).is_some() {
             unreachable!("ICE: ValueId {value_id:?} was already in cache");
         }
+        debug_assert_eq!(self.validate_allocation(function_context, value_id, dfg), Ok(()));
 
         self.available_variables.insert(value_id);
+        self.total_allocated += 1;
 
         variable
     }
@@ -114,11 +123,67 @@ impl BlockVariables {
         brillig_context.deallocate_register(variable.extract_register());
     }
 
+    /// Makes already allocated variables available within this block, e.g. the values live out of
+    /// its predecessors. No registers are allocated for them.
+    pub(crate) fn extend_available(
+        &mut self,
+        value_ids: impl IntoIterator<Item = ValueId>,
+        function_context: &FunctionContext,
+    ) {
+        for value_id in value_ids {
+            assert!(
+                function_context.ssa_value_allocations.contains_key(&value_id),
+                "ICE: Value {value_id} has not been allocated"
+            );
+            self.available_variables.insert(value_id);
+        }
+    }
+
     /// Checks if a variable is allocated.
     pub(crate) fn is_allocated(&self, value_id: &ValueId) -> bool {
         self.available_variables.contains(value_id)
     }
 
+    /// Returns the number of variables defined in this block so far.
+    ///
+    /// Unlike the number of available variables, this also counts variables which have since been removed.
+    pub(crate) fn total_allocated(&self) -> usize {
+        self.total_allocated
+    }
+
+    /// Checks that the cached allocation of a value has the shape expected from its SSA type,
+    /// returning a description of the mismatch otherwise.
+    pub(crate) fn validate_allocation(
+        &self,
+        function_context: &FunctionContext,
+        value_id: ValueId,
+        dfg: &DataFlowGraph,
+    ) -> Result<(), String> {
+        let variable = function_context
+            .ssa_value_allocations
+            .get(&value_id)
+            .ok_or_else(|| format!("Value {value_id} has not been allocated"))?;
+
+        let typ = dfg.type_of_value(value_id);
+        let is_valid = match (&typ, variable) {
+            (
+                Type::Numeric(_) | Type::Reference(_) | Type::Function,
+                BrilligVariable::SingleAddr(_),
+            ) => true,
+            (Type::Array(item_typ, elem_count), BrilligVariable::BrilligArray(array)) => {
+                array.size == compute_array_length(item_typ, *elem_count as usize)
+            }
+            (Type::Slice(_), BrilligVariable::BrilligVector(_)) => true,
+            _ => false,
+        };
+
+        if is_valid {
+            Ok(())
+        } else {
+            Err(format!("Value {value_id} of type {typ} has mismatched allocation {variable:?}"))
+        }
+    }
+
     /// For a given SSA value id, return the corresponding cached allocation.
     pub(crate) fn get_allocation(
         &mut self,
@@ -174,3 +239,88 @@ pub(crate) fn allocate_value_with_type<F, Registers: RegisterAllocator>(
         }),
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use acvm::FieldElement;
+
+    use crate::brillig::brillig_gen::brillig_fn::FunctionContext;
+    use crate::brillig::brillig_ir::BrilligContext;
+    use crate::brillig::brillig_ir::brillig_variable::{BrilligArray, BrilligVariable};
+    use crate::brillig::brillig_ir::registers::Stack;
+    use crate::brillig::brillig_ir::tests::create_context;
+    use crate::ssa::function_builder::FunctionBuilder;
+    use crate::ssa::ir::function::RuntimeType;
+    use crate::ssa::ir::map::Id;
+    use crate::ssa::ir::types::Type;
+    use crate::ssa::ir::value::ValueId;
+    use crate::ssa::ssa_gen::Ssa;
+
+    use super::BlockVariables;
+
+    /// Creates a brillig function taking `num_params` field parameters, returning the parameters
+    /// alongside the contexts needed to define them as variables.
+    fn create_test_environment(
+        num_params: usize,
+    ) -> (Ssa, Vec<ValueId>, FunctionContext, BrilligContext<FieldElement, Stack>) {
+        let mut builder = FunctionBuilder::new("main".to_string(), Id::test_new(0));
+        builder.set_runtime(RuntimeType::Brillig(Default::default()));
+        let params = (0..num_params).map(|_| builder.add_parameter(Type::field())).collect();
+        builder.terminate_with_return(vec![]);
+        let ssa = builder.finish();
+
+        let brillig_context = create_context(ssa.main_id);
+        let function_context = FunctionContext::new(ssa.main(), true);
+        (ssa, params, function_context, brillig_context)
+    }
+
+    #[test]
+    fn total_allocated_counts_removed_variables() {
+        let (ssa, params, mut function_context, mut brillig_context) = create_test_environment(3);
+        let dfg = &ssa.main().dfg;
+        let mut variables = BlockVariables::default();
+
+        variables.define_variable(&mut function_context, &mut brillig_context, params[0], dfg);
+        variables.define_variable(&mut function_context, &mut brillig_context, params[1], dfg);
+        assert_eq!(variables.total_allocated(), 2);
+
+        variables.remove_variable(&params[0], &mut function_context, &mut brillig_context);
+        assert_eq!(variables.total_allocated(), 2);
+
+        variables.define_variable(&mut function_context, &mut brillig_context, params[2], dfg);
+        assert_eq!(variables.total_allocated(), 3);
+        assert_eq!(variables.get_available_variables(&function_context).len(), 2);
+    }
+
+    #[test]
+    fn validate_allocation_reports_mismatched_shape() {
+        let (ssa, params, mut function_context, mut brillig_context) = create_test_environment(2);
+        let dfg = &ssa.main().dfg;
+        let mut variables = BlockVariables::default();
+
+        variables.define_variable(&mut function_context, &mut brillig_context, params[0], dfg);
+        assert_eq!(variables.validate_allocation(&function_context, params[0], dfg), Ok(()));
+
+        // Allocate the numeric parameter as if it were an array
+        let array = BrilligArray { pointer: brillig_context.allocate_register(), size: 1 };
+        function_context
+            .ssa_value_allocations
+            .insert(params[1], BrilligVariable::BrilligArray(array));
+        assert!(variables.validate_allocation(&function_context, params[1], dfg).is_err());
+    }
+
+    #[test]
+    fn extend_available_makes_allocated_variables_available() {
+        let (ssa, params, mut function_context, mut brillig_context) = create_test_environment(2);
+        let dfg = &ssa.main().dfg;
+        let mut predecessor = BlockVariables::default();
+        predecessor.define_variable(&mut function_context, &mut brillig_context, params[0], dfg);
+        predecessor.define_variable(&mut function_context, &mut brillig_context, params[1], dfg);
+
+        let mut variables = BlockVariables::default();
+        variables.extend_available(params.clone(), &function_context);
+        assert!(variables.is_allocated(&params[0]));
+        assert!(variables.is_allocated(&params[1]));
+        assert_eq!(variables.total_allocated(), 0);
+    }
+}
@@ -24,6 +24,8 @@ pub enum RuntimeError {
     InternalError(#[from] InternalError),
     #[error("Range constraint of {num_bits} bits is too large for the Field size")]
     InvalidRangeConstraint { num_bits: u32, call_stack: CallStack },
+    #[error("Division of {num_bits}-bit integers by a non-constant value is not supported")]
+    UnsupportedDivisionBitSize { num_bits: u32, call_stack: CallStack },
     #[error("The value `{value}` cannot fit into `{typ}` which has range `{range}`")]
     IntegerOutOfBounds {
         value: SignedField,
@@ -189,6 +191,7 @@ impl RuntimeError {
                 | InternalError::Unexpected { call_stack, .. },
             )
             | RuntimeError::InvalidRangeConstraint { call_stack, .. }
+            | RuntimeError::UnsupportedDivisionBitSize { call_stack, .. }
             | RuntimeError::TypeConversion { call_stack, .. }
             | RuntimeError::UnknownLoopBound { call_stack }
             | RuntimeError::AssertConstantFailed { call_stack }
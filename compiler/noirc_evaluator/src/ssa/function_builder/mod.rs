@@ -3,6 +3,8 @@ pub mod data_bus;
 use std::{borrow::Cow, collections::BTreeMap, sync::Arc};
 
 use acvm::{FieldElement, acir::circuit::ErrorSelector};
+use fxhash::FxHashMap as HashMap;
+use iter_extended::vecmap;
 use noirc_errors::{
     Location,
     call_stack::{CallStack, CallStackId},
@@ -15,7 +17,7 @@ use crate::ssa::ir::{
     function::{Function, FunctionId},
     instruction::{Binary, BinaryOp, Instruction, TerminatorInstruction},
     types::Type,
-    value::{Value, ValueId},
+    value::{Value, ValueId, ValueMapping},
 };
 
 use super::{
@@ -23,7 +25,7 @@ use super::{
         basic_block::BasicBlock,
         dfg::{GlobalsGraph, InsertInstructionResult},
         function::RuntimeType,
-        instruction::{ArrayOffset, ConstrainError, InstructionId, Intrinsic},
+        instruction::{ArrayOffset, ConstrainError, Endian, InstructionId, Intrinsic},
         types::NumericType,
     },
     opt::pure::FunctionPurities,
@@ -50,6 +52,40 @@ pub struct FunctionBuilder {
 
     globals: Arc<GlobalsGraph>,
     purities: Arc<FunctionPurities>,
+
+    /// Arrays built by `insert_make_array` in the current function, if deduplicating them is enabled.
+    /// Arrays are only reused within the block they were created in so that they always dominate their uses.
+    make_array_cache: Option<HashMap<MakeArrayKey, ValueId>>,
+
+    /// The condition of the innermost `with_side_effects_condition` scope being built, if any.
+    side_effects_condition: Option<ValueId>,
+}
+
+/// The block, elements and type of an array built by `insert_make_array`.
+type MakeArrayKey = (BasicBlockId, im::Vector<ValueId>, Type);
+
+/// Restores the side effects condition which was active before a
+/// [`FunctionBuilder::with_side_effects_condition`] scope once the scope ends.
+struct SideEffectsGuard<'a> {
+    builder: &'a mut FunctionBuilder,
+    previous_condition: Option<ValueId>,
+}
+
+impl Drop for SideEffectsGuard<'_> {
+    fn drop(&mut self) {
+        self.builder.side_effects_condition = self.previous_condition;
+
+        // If the scope is unwinding the function being built is abandoned, and inserting
+        // another instruction could panic again while already panicking.
+        if std::thread::panicking() {
+            return;
+        }
+        let condition = match self.previous_condition {
+            Some(condition) => condition,
+            None => self.builder.numeric_constant(true, NumericType::bool()),
+        };
+        self.builder.insert_enable_side_effects_if(condition);
+    }
 }
 
 impl FunctionBuilder {
@@ -68,6 +104,8 @@ impl FunctionBuilder {
             simplify: true,
             globals: Default::default(),
             purities: Default::default(),
+            make_array_cache: None,
+            side_effects_condition: None,
         }
     }
 
@@ -107,6 +145,19 @@ impl FunctionBuilder {
         self.current_function.set_globals(self.globals.clone());
     }
 
+    /// Enables or disables reusing the result of an identical `make_array` instruction
+    /// previously inserted by `insert_make_array`. This is disabled by default.
+    ///
+    /// Arrays are only reused in ACIR functions. Brillig arrays are reference counted and
+    /// may be mutated in place, so reusing one could alias two logically distinct arrays.
+    pub fn enable_make_array_dedup(&mut self, enabled: bool) {
+        if enabled {
+            self.make_array_cache.get_or_insert_with(HashMap::default);
+        } else {
+            self.make_array_cache = None;
+        }
+    }
+
     pub fn set_purities(&mut self, purities: Arc<FunctionPurities>) {
         self.purities = purities.clone();
         self.current_function.dfg.set_function_purities(purities);
@@ -136,6 +187,11 @@ impl FunctionBuilder {
 
         self.current_function.dfg.set_function_purities(self.purities.clone());
         self.apply_globals();
+
+        if let Some(cache) = &mut self.make_array_cache {
+            cache.clear();
+        }
+        self.side_effects_condition = None;
     }
 
     /// Finish the current function and create a new ACIR function.
@@ -153,6 +209,25 @@ impl FunctionBuilder {
         self.new_function_with_type(name, function_id, RuntimeType::Brillig(inline_type));
     }
 
+    /// Finish the current function and reuse this builder to create a new constrained function.
+    ///
+    /// This is equivalent to [`FunctionBuilder::new_function`]. The globals and function purities of
+    /// this builder are shared with the new function rather than copied, so driver code building many
+    /// functions should prefer reusing a single builder over creating a new builder per function.
+    pub fn reset_to_new_function(
+        &mut self,
+        name: String,
+        function_id: FunctionId,
+        inline_type: InlineType,
+    ) {
+        self.new_function(name, function_id, inline_type);
+    }
+
+    /// Returns the number of functions which have been finished, excluding the current function.
+    pub fn finished_function_count(&self) -> usize {
+        self.finished_functions.len()
+    }
+
     /// Consume the FunctionBuilder returning all the functions it has generated.
     pub fn finish(mut self) -> Ssa {
         self.finished_functions.push(self.current_function);
@@ -235,6 +310,35 @@ impl FunctionBuilder {
         }
     }
 
+    /// Insert each instruction of a pre-built sequence at the end of the current block, in order.
+    ///
+    /// Each entry holds an instruction, the values it produced in the sequence it was taken from,
+    /// and its control type variables. The operands of each instruction are remapped through
+    /// `value_map` before insertion (unmapped values are used as-is) and the values it produced
+    /// are then mapped to the new results, so later instructions can refer to earlier ones.
+    ///
+    /// Returns the results of all inserted instructions, in order.
+    pub fn insert_instructions(
+        &mut self,
+        instructions: Vec<(Instruction, Vec<ValueId>, Option<Vec<Type>>)>,
+        value_map: &mut HashMap<ValueId, ValueId>,
+    ) -> Vec<ValueId> {
+        let mut all_results = Vec::new();
+        for (instruction, old_results, ctrl_typevars) in instructions {
+            let instruction =
+                instruction.map_values(|value| value_map.get(&value).copied().unwrap_or(value));
+            let new_results = self.insert_instruction(instruction, ctrl_typevars).results();
+            assert_eq!(
+                old_results.len(),
+                new_results.len(),
+                "ICE: instruction produced a different number of results than expected"
+            );
+            value_map.extend(old_results.into_iter().zip(new_results.iter().copied()));
+            all_results.extend(new_results.iter().copied());
+        }
+        all_results
+    }
+
     /// Switch to inserting instructions in the given block.
     /// Expects the given block to be within the same function. If you want to insert
     /// instructions into a new function, call new_function instead.
@@ -242,15 +346,42 @@ impl FunctionBuilder {
         self.current_block = block;
     }
 
+    /// Runs `f` while inserting instructions into `block`, then switches back to the block
+    /// which was being inserted into beforehand.
+    pub fn with_block<T>(&mut self, block: BasicBlockId, f: impl FnOnce(&mut Self) -> T) -> T {
+        let previous_block = self.current_block;
+        self.switch_to_block(block);
+        let result = f(self);
+        self.switch_to_block(previous_block);
+        result
+    }
+
     /// Returns the block currently being inserted into
     pub(crate) fn current_block(&mut self) -> BasicBlockId {
         self.current_block
     }
 
+    /// Returns whether the current block already has a terminator instruction,
+    /// in which case no more instructions should be inserted into it.
+    pub fn current_block_is_terminated(&self) -> bool {
+        self.current_function.dfg[self.current_block].terminator().is_some()
+    }
+
     pub fn get_current_block_index(&self) -> BasicBlockId {
         self.current_block
     }
 
+    /// Replaces every use of `old` with `new` in the instructions and terminator of the
+    /// current block. Other blocks are left untouched.
+    ///
+    /// This is a low-level rewrite which does not check that `new` dominates each use of `old`,
+    /// so misusing it can break SSA invariants.
+    pub fn replace_value_in_current_block(&mut self, old: ValueId, new: ValueId) {
+        let mut mapping = ValueMapping::default();
+        mapping.insert(old, new);
+        self.current_function.dfg.replace_values_in_block(self.current_block, &mapping);
+    }
+
     /// Insert an allocate instruction at the end of the current block, allocating the
     /// given amount of field elements. Returns the result of the allocate instruction,
     /// which is always a Reference to the allocated data.
@@ -259,6 +390,43 @@ impl FunctionBuilder {
         self.insert_instruction(Instruction::Allocate, Some(vec![reference_type])).first()
     }
 
+    /// Insert an allocate instruction for a region of `count` slots of `element_type`, followed
+    /// by a single store of an array of `count` zero values into it. Returns the reference to the
+    /// region, which is always a reference to a `[element_type; count]` array. If `count` is zero
+    /// there is nothing to zero, so the region is only allocated.
+    ///
+    /// The whole region is zeroed by one store of a `make_array` rather than one store per slot,
+    /// as SSA has no instruction to compute the address of a single slot within an allocation.
+    ///
+    /// Panics if `element_type` contains a reference or function type, as these have no zero value.
+    pub fn insert_allocate_zeroed(&mut self, element_type: Type, count: usize) -> ValueId {
+        let region_type = Type::Array(Arc::new(vec![element_type.clone()]), count as u32);
+        let reference = self.insert_allocate(region_type.clone());
+        if count > 0 {
+            let zero = self.zero_value(&element_type);
+            let zeroes = self.insert_make_array(im::Vector::from(vec![zero; count]), region_type);
+            self.insert_store(reference, zeroes);
+        }
+        reference
+    }
+
+    /// Returns the zero value of `typ`: zero for numeric types, arrays of zero values for arrays
+    /// and an empty slice for slices.
+    fn zero_value(&mut self, typ: &Type) -> ValueId {
+        match typ {
+            Type::Numeric(numeric_type) => self.numeric_constant(0_u128, *numeric_type),
+            Type::Array(element_types, length) => {
+                let zeroes = vecmap(element_types.iter(), |typ| self.zero_value(typ));
+                let elements = (0..*length).flat_map(|_| zeroes.iter().copied()).collect();
+                self.insert_make_array(elements, typ.clone())
+            }
+            Type::Slice(_) => self.insert_make_array(im::Vector::new(), typ.clone()),
+            Type::Reference(_) | Type::Function => {
+                panic!("Cannot zero-initialize a value of type {typ}")
+            }
+        }
+    }
+
     pub fn set_location(&mut self, location: Location) -> &mut FunctionBuilder {
         self.call_stack = self.current_function.dfg.call_stack_data.add_location_to_root(location);
         self
@@ -295,6 +463,17 @@ impl FunctionBuilder {
         self.insert_instruction(instruction, None).first()
     }
 
+    /// Insert a comparison at the end of the current block. Returns its boolean result.
+    ///
+    /// Panics if `operator` is not a comparison operator.
+    pub fn insert_comparison(&mut self, lhs: ValueId, operator: BinaryOp, rhs: ValueId) -> ValueId {
+        assert!(
+            matches!(operator, BinaryOp::Eq | BinaryOp::Lt),
+            "Expected a comparison operator, found {operator}"
+        );
+        self.insert_binary(lhs, operator, rhs)
+    }
+
     /// Insert a not instruction at the end of the current block.
     /// Returns the result of the instruction.
     pub fn insert_not(&mut self, rhs: ValueId) -> ValueId {
@@ -308,6 +487,16 @@ impl FunctionBuilder {
         self.insert_instruction(Instruction::Cast(value, typ), None).first()
     }
 
+    /// Insert a cast instruction at the end of the current block, unless `value` already has the numeric type `typ`.
+    /// Returns the result of the cast instruction, or `value` itself if no cast was needed.
+    pub fn insert_cast_if_needed(&mut self, value: ValueId, typ: NumericType) -> ValueId {
+        if self.type_of_value(value) == Type::Numeric(typ) {
+            value
+        } else {
+            self.insert_cast(value, typ)
+        }
+    }
+
     /// Insert a truncate instruction at the end of the current block.
     /// Returns the result of the truncate instruction.
     pub fn insert_truncate(&mut self, value: ValueId, bit_size: u32, max_bit_size: u32) -> ValueId {
@@ -315,6 +504,25 @@ impl FunctionBuilder {
             .first()
     }
 
+    /// Insert a truncate instruction at the end of the current block, unless `value` is known to fit in
+    /// `bit_size` bits because `max_bit_size <= bit_size`. Returns the result of the truncate instruction,
+    /// or `value` itself if no truncation was needed.
+    ///
+    /// The type of `value` isn't used to skip the truncation, as values can hold more bits than their
+    /// type, e.g. the result of an overflowing addition before it is truncated back to its type.
+    pub fn insert_truncate_if_needed(
+        &mut self,
+        value: ValueId,
+        bit_size: u32,
+        max_bit_size: u32,
+    ) -> ValueId {
+        if max_bit_size <= bit_size {
+            value
+        } else {
+            self.insert_truncate(value, bit_size, max_bit_size)
+        }
+    }
+
     /// Insert a constrain instruction at the end of the current block.
     pub fn insert_constrain(
         &mut self,
@@ -349,6 +557,43 @@ impl FunctionBuilder {
         self.insert_instruction(Instruction::Call { func, arguments }, Some(result_types)).results()
     }
 
+    /// Insert a call to the `to_le_bits` or `to_be_bits` intrinsic decomposing `value` into `num_bits` bits.
+    /// Returns the resulting array of booleans.
+    pub fn insert_to_bits(&mut self, value: ValueId, num_bits: u32, endian: Endian) -> ValueId {
+        let to_bits = self.import_intrinsic_id(Intrinsic::ToBits(endian));
+        let length = self.length_constant(num_bits);
+        let result_types = vec![Type::Array(Arc::new(vec![Type::bool()]), num_bits)];
+        self.insert_call(to_bits, vec![value, length], result_types)[0]
+    }
+
+    /// Insert a call to the `array_len` intrinsic, returning the length of `array`.
+    ///
+    /// `array` holds the intrinsic's arguments: a single value for a fixed-size array, or the
+    /// `[length, contents]` pair a slice is represented by in SSA. In both cases the call is
+    /// simplified away, to a length constant or to the slice's length value respectively.
+    pub fn insert_array_len(&mut self, array: &[ValueId]) -> ValueId {
+        let array_len = self.import_intrinsic_id(Intrinsic::ArrayLen);
+        self.insert_call(array_len, array.to_vec(), vec![Type::length_type()])[0]
+    }
+
+    /// Insert a constraint that `slice_a` and `slice_b` have the same length.
+    /// Each side is passed as for [`Self::insert_array_len`].
+    /// The message defaults to "slices must have equal length".
+    ///
+    /// When both lengths are known at compile-time the constraint folds away entirely
+    /// if they match, and remains as a statically failing constraint if they don't.
+    pub fn insert_assert_eq_len(
+        &mut self,
+        slice_a: &[ValueId],
+        slice_b: &[ValueId],
+        message: Option<String>,
+    ) {
+        let len_a = self.insert_array_len(slice_a);
+        let len_b = self.insert_array_len(slice_b);
+        let message = message.unwrap_or_else(|| "slices must have equal length".to_string());
+        self.insert_constrain(len_a, len_b, Some(ConstrainError::StaticString(message)));
+    }
+
     /// Insert an instruction to extract an element from an array
     pub fn insert_array_get(
         &mut self,
@@ -362,6 +607,22 @@ impl FunctionBuilder {
             .first()
     }
 
+    /// Insert an instruction to extract an element from a slice, preceded by a constraint that
+    /// `index` is less than the slice's `length`.
+    pub fn insert_slice_get_checked(
+        &mut self,
+        slice: ValueId,
+        index: ValueId,
+        length: ValueId,
+        element_type: Type,
+    ) -> ValueId {
+        let in_bounds = self.insert_binary(index, BinaryOp::Lt, length);
+        let true_value = self.numeric_constant(true, NumericType::bool());
+        let message = ConstrainError::StaticString("slice index out of bounds".to_string());
+        self.insert_constrain(in_bounds, true_value, Some(message));
+        self.insert_array_get(slice, index, ArrayOffset::None, element_type)
+    }
+
     /// Insert an instruction to create a new array with the given index replaced with a new value
     pub fn insert_array_set(
         &mut self,
@@ -375,6 +636,28 @@ impl FunctionBuilder {
         self.insert_instruction(instruction, None).first()
     }
 
+    /// Insert an instruction to create a new array with the given index replaced with a new value,
+    /// preceded by a constraint that `index` is less than the array's `length`.
+    ///
+    /// The constraint fails with `message`, or "index out of bounds" if no message is given.
+    #[allow(clippy::too_many_arguments)]
+    pub fn insert_array_set_checked(
+        &mut self,
+        array: ValueId,
+        index: ValueId,
+        value: ValueId,
+        length: ValueId,
+        mutable: bool,
+        offset: ArrayOffset,
+        message: Option<String>,
+    ) -> ValueId {
+        let in_bounds = self.insert_binary(index, BinaryOp::Lt, length);
+        let true_value = self.numeric_constant(true, NumericType::bool());
+        let message = message.unwrap_or_else(|| "index out of bounds".to_string());
+        self.insert_constrain(in_bounds, true_value, Some(ConstrainError::StaticString(message)));
+        self.insert_array_set(array, index, value, mutable, offset)
+    }
+
     /// Insert an instruction to increment an array's reference count. This only has an effect
     /// in unconstrained code where arrays are reference counted and copy on write.
     pub fn insert_inc_rc(&mut self, value: ValueId) {
@@ -393,11 +676,47 @@ impl FunctionBuilder {
         self.insert_instruction(Instruction::EnableSideEffectsIf { condition }, None);
     }
 
+    /// Runs `f` with side effects enabled only under `condition`, then restores the condition of the
+    /// enclosing `with_side_effects_condition` scope, or the always-true condition outside of any scope.
+    ///
+    /// The previous condition is restored by a guard, so scopes stay balanced even if `f` panics.
+    pub fn with_side_effects_condition<T>(
+        &mut self,
+        condition: ValueId,
+        f: impl FnOnce(&mut Self) -> T,
+    ) -> T {
+        let previous_condition = self.side_effects_condition.replace(condition);
+        self.insert_enable_side_effects_if(condition);
+        let guard = SideEffectsGuard { builder: self, previous_condition };
+        f(&mut *guard.builder)
+    }
+
+    /// Insert a Store instruction which is only enabled when `condition` is true, followed by
+    /// restoring the previous side effects condition.
+    pub fn insert_conditional_store(
+        &mut self,
+        address: ValueId,
+        value: ValueId,
+        condition: ValueId,
+    ) {
+        self.with_side_effects_condition(condition, |builder| builder.insert_store(address, value));
+    }
+
     /// Insert a `make_array` instruction to create a new array or slice.
     /// Returns the new array value. Expects `typ` to be an array or slice type.
     pub fn insert_make_array(&mut self, elements: im::Vector<ValueId>, typ: Type) -> ValueId {
         assert!(matches!(typ, Type::Array(..) | Type::Slice(_)));
-        self.insert_instruction(Instruction::MakeArray { elements, typ }, None).first()
+        let key = (self.make_array_cache.is_some() && self.current_function.runtime().is_acir())
+            .then(|| (self.current_block, elements.clone(), typ.clone()));
+        if let Some(array) = key.as_ref().and_then(|key| self.make_array_cache.as_ref()?.get(key)) {
+            return *array;
+        }
+
+        let array = self.insert_instruction(Instruction::MakeArray { elements, typ }, None).first();
+        if let (Some(cache), Some(key)) = (&mut self.make_array_cache, key) {
+            cache.insert(key, array);
+        }
+        array
     }
 
     /// Terminates the current block with the given terminator instruction
@@ -448,6 +767,19 @@ impl FunctionBuilder {
         self.terminate_block_with(TerminatorInstruction::Unreachable { call_stack });
     }
 
+    /// Terminate the current block with an unreachable instruction, preceded by a
+    /// constraint which always fails with the given message.
+    ///
+    /// If control flow does reach this block at runtime the user is shown `message`
+    /// rather than a bare unreachable failure.
+    pub fn terminate_with_unreachable_msg(&mut self, message: &str) {
+        let zero = self.numeric_constant(false, NumericType::bool());
+        let one = self.numeric_constant(true, NumericType::bool());
+        let message = ConstrainError::StaticString(message.to_string());
+        self.insert_constrain(zero, one, Some(message));
+        self.terminate_with_unreachable();
+    }
+
     /// Returns a ValueId pointing to the given function or imports the function
     /// into the current function if it was not already, and returns that ID.
     pub fn import_function(&mut self, function: FunctionId) -> ValueId {
@@ -582,12 +914,15 @@ mod tests {
     use acvm::{FieldElement, acir::AcirField};
 
     use crate::ssa::ir::{
-        instruction::{Endian, Intrinsic},
+        instruction::{
+            ArrayOffset, Binary, BinaryOp, ConstrainError, Endian, Instruction, Intrinsic,
+            TerminatorInstruction,
+        },
         map::Id,
         types::{NumericType, Type},
     };
 
-    use super::FunctionBuilder;
+    use super::{FunctionBuilder, HashMap, InlineType};
 
     #[test]
     fn insert_constant_call() {
@@ -612,4 +947,558 @@ mod tests {
         assert_eq!(slice[2], one);
         assert_eq!(slice[3], zero);
     }
+
+    #[test]
+    fn replace_value_in_current_block() {
+        let func_id = Id::test_new(0);
+        let mut builder = FunctionBuilder::new("func".into(), func_id);
+        let v0 = builder.add_parameter(Type::field());
+        let v1 = builder.add_parameter(Type::field());
+        let v2 = builder.add_parameter(Type::field());
+
+        builder.insert_binary(v0, BinaryOp::Add { unchecked: false }, v1);
+        builder.insert_binary(v0, BinaryOp::Mul { unchecked: false }, v1);
+        builder.terminate_with_return(vec![v0]);
+
+        builder.replace_value_in_current_block(v0, v2);
+
+        let entry = builder.current_function.entry_block();
+        let instructions = builder.current_function.dfg[entry].instructions();
+        assert_eq!(instructions.len(), 2);
+        for instruction in instructions {
+            let Instruction::Binary(Binary { lhs, rhs, .. }) = &builder[*instruction] else {
+                panic!("expected a binary instruction");
+            };
+            assert_eq!(*lhs, v2);
+            assert_eq!(*rhs, v1);
+        }
+        let Some(TerminatorInstruction::Return { return_values, .. }) =
+            builder.current_function.dfg[entry].terminator()
+        else {
+            panic!("expected a return terminator");
+        };
+        assert_eq!(return_values, &vec![v2]);
+    }
+
+    #[test]
+    fn with_side_effects_condition() {
+        let func_id = Id::test_new(0);
+        let mut builder = FunctionBuilder::new("func".into(), func_id);
+        let condition = builder.add_parameter(Type::bool());
+        let v1 = builder.add_parameter(Type::field());
+        let v2 = builder.add_parameter(Type::field());
+
+        let sum = builder.with_side_effects_condition(condition, |builder| {
+            builder.insert_binary(v1, BinaryOp::Add { unchecked: false }, v2)
+        });
+
+        let entry = builder.current_function.entry_block();
+        let instructions = builder.current_function.dfg[entry].instructions();
+        assert_eq!(instructions.len(), 3);
+        assert_eq!(builder[instructions[0]], Instruction::EnableSideEffectsIf { condition });
+        assert_eq!(builder.current_function.dfg.instruction_results(instructions[1]), &[sum]);
+        let Instruction::EnableSideEffectsIf { condition: restored } = builder[instructions[2]]
+        else {
+            panic!("expected an enable_side_effects_if instruction");
+        };
+        assert_eq!(
+            builder.current_function.dfg.get_numeric_constant(restored),
+            Some(FieldElement::one())
+        );
+    }
+
+    #[test]
+    fn with_side_effects_condition_nested() {
+        let func_id = Id::test_new(0);
+        let mut builder = FunctionBuilder::new("func".into(), func_id);
+        let outer = builder.add_parameter(Type::bool());
+        let inner = builder.add_parameter(Type::bool());
+        let v2 = builder.add_parameter(Type::field());
+
+        builder.with_side_effects_condition(outer, |builder| {
+            builder.with_side_effects_condition(inner, |builder| {
+                builder.insert_binary(v2, BinaryOp::Add { unchecked: false }, v2)
+            })
+        });
+
+        // The inner scope restores the outer condition rather than the always-true condition
+        let entry = builder.current_function.entry_block();
+        let instructions = builder.current_function.dfg[entry].instructions();
+        assert_eq!(instructions.len(), 5);
+        assert_eq!(builder[instructions[0]], Instruction::EnableSideEffectsIf { condition: outer });
+        assert_eq!(builder[instructions[1]], Instruction::EnableSideEffectsIf { condition: inner });
+        assert_eq!(builder[instructions[3]], Instruction::EnableSideEffectsIf { condition: outer });
+        let Instruction::EnableSideEffectsIf { condition: restored } = builder[instructions[4]]
+        else {
+            panic!("expected an enable_side_effects_if instruction");
+        };
+        assert_eq!(
+            builder.current_function.dfg.get_numeric_constant(restored),
+            Some(FieldElement::one())
+        );
+    }
+
+    #[test]
+    fn terminate_with_unreachable_msg() {
+        let func_id = Id::test_new(0);
+        let mut builder = FunctionBuilder::new("func".into(), func_id);
+        builder.terminate_with_unreachable_msg("should not get here");
+
+        let entry = builder.current_function.entry_block();
+        let instructions = builder.current_function.dfg[entry].instructions();
+        assert_eq!(instructions.len(), 1);
+        let Instruction::Constrain(lhs, rhs, Some(message)) = &builder[instructions[0]] else {
+            panic!("expected a constrain instruction with a message");
+        };
+        let dfg = &builder.current_function.dfg;
+        assert_eq!(dfg.get_numeric_constant(*lhs), Some(FieldElement::zero()));
+        assert_eq!(dfg.get_numeric_constant(*rhs), Some(FieldElement::one()));
+        assert_eq!(message, &ConstrainError::StaticString("should not get here".to_string()));
+        assert!(matches!(dfg[entry].terminator(), Some(TerminatorInstruction::Unreachable { .. })));
+    }
+
+    #[test]
+    fn make_array_dedup() {
+        let func_id = Id::test_new(0);
+        let mut builder = FunctionBuilder::new("func".into(), func_id);
+        let one = builder.field_constant(1_u128);
+        let two = builder.field_constant(2_u128);
+        let typ = Type::Array(Arc::new(vec![Type::field()]), 2);
+
+        let first = builder.insert_make_array(im::vector![one, two], typ.clone());
+        let second = builder.insert_make_array(im::vector![one, two], typ.clone());
+        assert_ne!(first, second);
+
+        builder.enable_make_array_dedup(true);
+        let first = builder.insert_make_array(im::vector![one, two], typ.clone());
+        let second = builder.insert_make_array(im::vector![one, two], typ.clone());
+        assert_eq!(first, second);
+        let other = builder.insert_make_array(im::vector![two, one], typ.clone());
+        assert_ne!(first, other);
+
+        // Arrays are not reused across blocks
+        let block = builder.insert_block();
+        builder.terminate_with_jmp(block, vec![]);
+        builder.switch_to_block(block);
+        let third = builder.insert_make_array(im::vector![one, two], typ.clone());
+        assert_ne!(first, third);
+
+        // The cache is cleared when starting a new function
+        builder.new_function("func2".into(), Id::test_new(1), InlineType::default());
+        let one = builder.field_constant(1_u128);
+        let two = builder.field_constant(2_u128);
+        let first = builder.insert_make_array(im::vector![one, two], typ.clone());
+        let second = builder.insert_make_array(im::vector![one, two], typ.clone());
+        assert_eq!(first, second);
+        let entry = builder.current_function.entry_block();
+        assert_eq!(builder.current_function.dfg[entry].instructions().len(), 1);
+
+        // Arrays are never reused in Brillig functions
+        builder.new_brillig_function("func3".into(), Id::test_new(2), InlineType::default());
+        let one = builder.field_constant(1_u128);
+        let two = builder.field_constant(2_u128);
+        let first = builder.insert_make_array(im::vector![one, two], typ.clone());
+        let second = builder.insert_make_array(im::vector![one, two], typ);
+        assert_ne!(first, second);
+    }
+
+    #[test]
+    fn insert_cast_if_needed() {
+        let func_id = Id::test_new(0);
+        let mut builder = FunctionBuilder::new("func".into(), func_id);
+        let v0 = builder.add_parameter(Type::unsigned(32));
+        let v1 = builder.add_parameter(Type::unsigned(8));
+
+        assert_eq!(builder.insert_cast_if_needed(v0, NumericType::unsigned(32)), v0);
+        let entry = builder.current_function.entry_block();
+        assert!(builder.current_function.dfg[entry].instructions().is_empty());
+
+        let v2 = builder.insert_cast_if_needed(v1, NumericType::unsigned(32));
+        let instructions = builder.current_function.dfg[entry].instructions();
+        assert_eq!(instructions.len(), 1);
+        assert_eq!(builder[instructions[0]], Instruction::Cast(v1, NumericType::unsigned(32)));
+        assert_eq!(builder.current_function.dfg.instruction_results(instructions[0]), &[v2]);
+    }
+
+    #[test]
+    fn insert_truncate_if_needed() {
+        let func_id = Id::test_new(0);
+        let mut builder = FunctionBuilder::new("func".into(), func_id);
+        let v0 = builder.add_parameter(Type::unsigned(8));
+        let v1 = builder.add_parameter(Type::unsigned(32));
+
+        assert_eq!(builder.insert_truncate_if_needed(v0, 8, 8), v0);
+        let entry = builder.current_function.entry_block();
+        assert!(builder.current_function.dfg[entry].instructions().is_empty());
+
+        // `v0` may hold more bits than its type, so the truncation is kept
+        let v2 = builder.insert_truncate_if_needed(v0, 8, 32);
+        let v3 = builder.insert_truncate_if_needed(v1, 8, 32);
+        let instructions = builder.current_function.dfg[entry].instructions();
+        assert_eq!(instructions.len(), 2);
+        assert_eq!(
+            builder[instructions[0]],
+            Instruction::Truncate { value: v0, bit_size: 8, max_bit_size: 32 }
+        );
+        assert_eq!(builder.current_function.dfg.instruction_results(instructions[0]), &[v2]);
+        assert_eq!(
+            builder[instructions[1]],
+            Instruction::Truncate { value: v1, bit_size: 8, max_bit_size: 32 }
+        );
+        assert_eq!(builder.current_function.dfg.instruction_results(instructions[1]), &[v3]);
+    }
+
+    #[test]
+    fn insert_comparison() {
+        let func_id = Id::test_new(0);
+        let mut builder = FunctionBuilder::new("func".into(), func_id);
+        let v0 = builder.add_parameter(Type::unsigned(32));
+        let v1 = builder.add_parameter(Type::unsigned(32));
+
+        let v2 = builder.insert_comparison(v0, BinaryOp::Lt, v1);
+        assert_eq!(builder.type_of_value(v2), Type::bool());
+    }
+
+    #[test]
+    #[should_panic(expected = "Expected a comparison operator")]
+    fn insert_comparison_rejects_arithmetic() {
+        let func_id = Id::test_new(0);
+        let mut builder = FunctionBuilder::new("func".into(), func_id);
+        let v0 = builder.add_parameter(Type::unsigned(32));
+        let v1 = builder.add_parameter(Type::unsigned(32));
+
+        builder.insert_comparison(v0, BinaryOp::Add { unchecked: false }, v1);
+    }
+
+    #[test]
+    fn reset_to_new_function() {
+        let mut builder = FunctionBuilder::new("func0".into(), Id::test_new(0));
+        assert_eq!(builder.finished_function_count(), 0);
+
+        for i in 1..=3 {
+            builder.terminate_with_return(vec![]);
+            builder.reset_to_new_function(format!("func{i}"), Id::test_new(i), InlineType::Inline);
+            assert_eq!(builder.finished_function_count(), i as usize);
+        }
+        builder.terminate_with_return(vec![]);
+
+        let ssa = builder.finish();
+        assert_eq!(ssa.functions.len(), 4);
+        assert_eq!(ssa.functions[&Id::test_new(3)].name(), "func3");
+    }
+
+    #[test]
+    fn current_block_is_terminated() {
+        let func_id = Id::test_new(0);
+        let mut builder = FunctionBuilder::new("func".into(), func_id);
+        assert!(!builder.current_block_is_terminated());
+
+        builder.terminate_with_return(vec![]);
+        assert!(builder.current_block_is_terminated());
+
+        let block = builder.insert_block();
+        builder.switch_to_block(block);
+        assert!(!builder.current_block_is_terminated());
+    }
+
+    #[test]
+    fn insert_to_bits() {
+        let func_id = Id::test_new(0);
+        let mut builder = FunctionBuilder::new("func".into(), func_id);
+        let one = builder.numeric_constant(FieldElement::one(), NumericType::bool());
+        let zero = builder.numeric_constant(FieldElement::zero(), NumericType::bool());
+
+        let input = builder.field_constant(FieldElement::from(7_u128));
+        let bits = builder.insert_to_bits(input, 8, Endian::Little);
+        assert_eq!(builder.type_of_value(bits), Type::Array(Arc::new(vec![Type::bool()]), 8));
+
+        let (bits, _) = builder.current_function.dfg.get_array_constant(bits).unwrap();
+        assert_eq!(bits.len(), 8);
+        assert_eq!(bits[0], one);
+        assert_eq!(bits[1], one);
+        assert_eq!(bits[2], one);
+        assert_eq!(bits[3], zero);
+    }
+
+    #[test]
+    fn insert_slice_get_checked() {
+        let func_id = Id::test_new(0);
+        let mut builder = FunctionBuilder::new("func".into(), func_id);
+        let slice = builder.add_parameter(Type::Slice(Arc::new(vec![Type::field()])));
+        let index = builder.add_parameter(Type::unsigned(32));
+        let length = builder.add_parameter(Type::unsigned(32));
+
+        let element = builder.insert_slice_get_checked(slice, index, length, Type::field());
+
+        let entry = builder.current_function.entry_block();
+        let instructions = builder.current_function.dfg[entry].instructions();
+        assert_eq!(instructions.len(), 3);
+        let in_bounds = builder.current_function.dfg.instruction_results(instructions[0])[0];
+        assert_eq!(
+            builder[instructions[0]],
+            Instruction::Binary(Binary { lhs: index, rhs: length, operator: BinaryOp::Lt })
+        );
+        let Instruction::Constrain(lhs, _, Some(message)) = &builder[instructions[1]] else {
+            panic!("expected a constrain instruction with a message");
+        };
+        assert_eq!(*lhs, in_bounds);
+        assert_eq!(message, &ConstrainError::StaticString("slice index out of bounds".to_string()));
+        assert_eq!(
+            builder[instructions[2]],
+            Instruction::ArrayGet { array: slice, index, offset: ArrayOffset::None }
+        );
+        assert_eq!(builder.current_function.dfg.instruction_results(instructions[2]), &[element]);
+    }
+
+    #[test]
+    fn with_block_restores_current_block() {
+        let func_id = Id::test_new(0);
+        let mut builder = FunctionBuilder::new("func".into(), func_id);
+        let entry = builder.current_function.entry_block();
+        let other = builder.insert_block();
+        let value = builder.add_parameter(Type::bool());
+
+        let inner = builder.with_block(other, |builder| builder.insert_not(value));
+        assert_eq!(builder.current_block(), entry);
+        let outer = builder.insert_not(value);
+
+        let inner_instructions = builder.current_function.dfg[other].instructions();
+        assert_eq!(inner_instructions.len(), 1);
+        assert_eq!(
+            builder.current_function.dfg.instruction_results(inner_instructions[0]),
+            &[inner]
+        );
+
+        let outer_instructions = builder.current_function.dfg[entry].instructions();
+        assert_eq!(outer_instructions.len(), 1);
+        assert_eq!(
+            builder.current_function.dfg.instruction_results(outer_instructions[0]),
+            &[outer]
+        );
+    }
+
+    #[test]
+    fn insert_allocate_zeroed() {
+        let func_id = Id::test_new(0);
+        let mut builder = FunctionBuilder::new("func".into(), func_id);
+        let reference = builder.insert_allocate_zeroed(Type::unsigned(32), 3);
+
+        let region_type = Type::Array(Arc::new(vec![Type::unsigned(32)]), 3);
+        assert_eq!(builder.type_of_value(reference), Type::Reference(Arc::new(region_type)));
+
+        // The region is zeroed by a single store of a `make_array` of three zeroes
+        let entry = builder.current_function.entry_block();
+        let instructions = builder.current_function.dfg[entry].instructions();
+        assert_eq!(instructions.len(), 3);
+        assert_eq!(builder[instructions[0]], Instruction::Allocate);
+        let Instruction::Store { address, value } = builder[instructions[2]] else {
+            panic!("expected a store instruction");
+        };
+        assert_eq!(address, reference);
+
+        let (zeroes, _) = builder.current_function.dfg.get_array_constant(value).unwrap();
+        assert_eq!(zeroes.len(), 3);
+        for zero in zeroes {
+            assert_eq!(
+                builder.current_function.dfg.get_numeric_constant(zero),
+                Some(FieldElement::zero())
+            );
+        }
+    }
+
+    #[test]
+    fn insert_allocate_zeroed_composite_elements() {
+        let func_id = Id::test_new(0);
+        let mut builder = FunctionBuilder::new("func".into(), func_id);
+        let element_type = Type::Array(Arc::new(vec![Type::field(), Type::bool()]), 2);
+        let reference = builder.insert_allocate_zeroed(element_type.clone(), 3);
+
+        let region_type = Type::Array(Arc::new(vec![element_type]), 3);
+        assert_eq!(builder.type_of_value(reference), Type::Reference(Arc::new(region_type)));
+
+        let entry = builder.current_function.entry_block();
+        let instructions = builder.current_function.dfg[entry].instructions();
+        let Instruction::Store { address, value } = builder[*instructions.last().unwrap()] else {
+            panic!("expected a store instruction");
+        };
+        assert_eq!(address, reference);
+
+        // Each slot is a `[(Field, bool); 2]` of zeroes
+        let dfg = &builder.current_function.dfg;
+        let (slots, _) = dfg.get_array_constant(value).unwrap();
+        assert_eq!(slots.len(), 3);
+        for slot in slots {
+            let (zeroes, _) = dfg.get_array_constant(slot).unwrap();
+            assert_eq!(zeroes.len(), 4);
+            for zero in zeroes {
+                assert_eq!(dfg.get_numeric_constant(zero), Some(FieldElement::zero()));
+            }
+        }
+    }
+
+    #[test]
+    fn insert_allocate_zeroed_empty_region() {
+        let func_id = Id::test_new(0);
+        let mut builder = FunctionBuilder::new("func".into(), func_id);
+        let reference = builder.insert_allocate_zeroed(Type::field(), 0);
+
+        let region_type = Type::Array(Arc::new(vec![Type::field()]), 0);
+        assert_eq!(builder.type_of_value(reference), Type::Reference(Arc::new(region_type)));
+
+        let entry = builder.current_function.entry_block();
+        let instructions = builder.current_function.dfg[entry].instructions();
+        assert_eq!(instructions.len(), 1);
+        assert_eq!(builder[instructions[0]], Instruction::Allocate);
+    }
+
+    #[test]
+    fn insert_conditional_store() {
+        let func_id = Id::test_new(0);
+        let mut builder = FunctionBuilder::new("func".into(), func_id);
+        let condition = builder.add_parameter(Type::bool());
+        let value = builder.add_parameter(Type::field());
+        let address = builder.insert_allocate(Type::field());
+
+        builder.insert_conditional_store(address, value, condition);
+
+        let entry = builder.current_function.entry_block();
+        let instructions = builder.current_function.dfg[entry].instructions();
+        assert_eq!(instructions.len(), 4);
+        assert_eq!(builder[instructions[1]], Instruction::EnableSideEffectsIf { condition });
+        assert_eq!(builder[instructions[2]], Instruction::Store { address, value });
+        let Instruction::EnableSideEffectsIf { condition: restored } = builder[instructions[3]]
+        else {
+            panic!("expected an enable_side_effects_if instruction");
+        };
+        assert_eq!(
+            builder.current_function.dfg.get_numeric_constant(restored),
+            Some(FieldElement::one())
+        );
+    }
+
+    #[test]
+    fn insert_array_set_checked() {
+        let func_id = Id::test_new(0);
+        let mut builder = FunctionBuilder::new("func".into(), func_id);
+        let array = builder.add_parameter(Type::Array(Arc::new(vec![Type::field()]), 3));
+        let index = builder.add_parameter(Type::unsigned(32));
+        let value = builder.add_parameter(Type::field());
+        let length = builder.length_constant(3_u128);
+
+        let new_array = builder.insert_array_set_checked(
+            array,
+            index,
+            value,
+            length,
+            true,
+            ArrayOffset::None,
+            None,
+        );
+
+        let entry = builder.current_function.entry_block();
+        let instructions = builder.current_function.dfg[entry].instructions();
+        assert_eq!(instructions.len(), 3);
+        let in_bounds = builder.current_function.dfg.instruction_results(instructions[0])[0];
+        assert_eq!(
+            builder[instructions[0]],
+            Instruction::Binary(Binary { lhs: index, rhs: length, operator: BinaryOp::Lt })
+        );
+        let Instruction::Constrain(lhs, _, Some(message)) = &builder[instructions[1]] else {
+            panic!("expected a constrain instruction with a message");
+        };
+        assert_eq!(*lhs, in_bounds);
+        assert_eq!(message, &ConstrainError::StaticString("index out of bounds".to_string()));
+        assert_eq!(
+            builder[instructions[2]],
+            Instruction::ArraySet { array, index, value, mutable: true, offset: ArrayOffset::None }
+        );
+        assert_eq!(builder.current_function.dfg.instruction_results(instructions[2]), &[new_array]);
+    }
+
+    #[test]
+    fn insert_assert_eq_len() {
+        let func_id = Id::test_new(0);
+        let mut builder = FunctionBuilder::new("func".into(), func_id);
+        let slice_type = Type::Slice(Arc::new(vec![Type::field()]));
+        let len_a = builder.add_parameter(Type::length_type());
+        let slice_a = builder.add_parameter(slice_type.clone());
+        let len_b = builder.add_parameter(Type::length_type());
+        let slice_b = builder.add_parameter(slice_type);
+
+        builder.insert_assert_eq_len(&[len_a, slice_a], &[len_b, slice_b], None);
+
+        let entry = builder.current_function.entry_block();
+        let instructions = builder.current_function.dfg[entry].instructions();
+        assert_eq!(instructions.len(), 1);
+        assert_eq!(
+            builder[instructions[0]],
+            Instruction::Constrain(
+                len_a,
+                len_b,
+                Some(ConstrainError::StaticString("slices must have equal length".to_string()))
+            )
+        );
+    }
+
+    #[test]
+    fn insert_assert_eq_len_folds_fixed_arrays() {
+        let func_id = Id::test_new(0);
+        let mut builder = FunctionBuilder::new("func".into(), func_id);
+        let array_type = Type::Array(Arc::new(vec![Type::field()]), 2);
+        let array_a = builder.add_parameter(array_type.clone());
+        let array_b = builder.add_parameter(array_type);
+
+        builder.insert_assert_eq_len(&[array_a], &[array_b], Some("lengths differ".to_string()));
+
+        let entry = builder.current_function.entry_block();
+        assert!(builder.current_function.dfg[entry].instructions().is_empty());
+    }
+
+    #[test]
+    fn insert_instructions_remaps_dependencies() {
+        let func_id = Id::test_new(0);
+        let mut builder = FunctionBuilder::new("func".into(), func_id);
+        let a = builder.add_parameter(Type::field());
+        let b = builder.add_parameter(Type::field());
+
+        // A template computing `(x + b) * x`, where `x` and the intermediate sum are placeholders
+        let (x, sum, product) = (Id::test_new(100), Id::test_new(101), Id::test_new(102));
+        let instructions = vec![
+            (
+                Instruction::Binary(Binary {
+                    lhs: x,
+                    rhs: b,
+                    operator: BinaryOp::Add { unchecked: false },
+                }),
+                vec![sum],
+                None,
+            ),
+            (
+                Instruction::Binary(Binary {
+                    lhs: sum,
+                    rhs: x,
+                    operator: BinaryOp::Mul { unchecked: false },
+                }),
+                vec![product],
+                None,
+            ),
+        ];
+        let mut value_map = HashMap::default();
+        value_map.insert(x, a);
+
+        let results = builder.insert_instructions(instructions, &mut value_map);
+
+        let entry = builder.current_function.entry_block();
+        let instructions = builder.current_function.dfg[entry].instructions();
+        assert_eq!(instructions.len(), 2);
+        assert_eq!(results, vec![value_map[&sum], value_map[&product]]);
+        assert_eq!(
+            builder[instructions[1]],
+            Instruction::Binary(Binary {
+                lhs: results[0],
+                rhs: a,
+                operator: BinaryOp::Mul { unchecked: false }
+            })
+        );
+    }
 }
@@ -458,9 +458,9 @@ pub fn convert_generated_acir_into_circuit(
 ) -> SsaCircuitArtifact {
     let opcodes = generated_acir.take_opcodes();
     let current_witness_index = generated_acir.current_witness_index().0;
+    let opcode_call_stacks = generated_acir.opcode_call_stacks();
     let GeneratedAcir {
         return_witnesses,
-        location_map,
         brillig_locations,
         input_witnesses,
         assertion_payloads: assert_messages,
@@ -485,10 +485,10 @@ pub fn convert_generated_acir_into_circuit(
         return_values,
         assert_messages: assert_messages.into_iter().collect(),
     };
-    let acir_location_map: BTreeMap<AcirOpcodeLocation, CallStackId> = location_map
-        .iter()
+    let acir_location_map: BTreeMap<AcirOpcodeLocation, CallStackId> = opcode_call_stacks
+        .into_iter()
         .map(|(k, v)| match k {
-            OpcodeLocation::Acir(index) => (AcirOpcodeLocation::new(*index), *v),
+            OpcodeLocation::Acir(index) => (AcirOpcodeLocation::new(index), v),
             OpcodeLocation::Brillig { .. } => unreachable!("Expected ACIR opcode"),
         })
         .collect();
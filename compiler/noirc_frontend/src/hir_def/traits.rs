@@ -1,13 +1,17 @@
+use acvm::blackbox_solver::blake3;
 use iter_extended::vecmap;
-use rustc_hash::FxHashMap as HashMap;
+use rustc_hash::{FxHashMap as HashMap, FxHashSet as HashSet};
+use std::collections::VecDeque;
+use std::rc::Rc;
 
 use crate::ResolvedGeneric;
 use crate::ast::{Ident, ItemVisibility, NoirFunction};
 use crate::hir::type_check::generics::TraitGenerics;
 use crate::node_interner::{DefinitionId, NodeInterner};
 use crate::{
-    Generics, Type, TypeBindings, TypeVariable,
+    Generics, NamedGeneric, Type, TypeBindings, TypeVariable,
     graph::CrateId,
+    hir::def_map::ModuleId,
     node_interner::{FuncId, TraitId},
 };
 use fm::FileId;
@@ -211,6 +215,133 @@ impl Trait {
         None
     }
 
+    /// Renders the signature of the method with the given name, e.g. `fn foo<U>(Self, U) -> T`.
+    ///
+    /// `Self` is always rendered as `Self`, even if the trait's self type variable is currently
+    /// bound to a concrete type. Returns `None` if the trait has no such method.
+    pub fn method_signature_string(&self, name: &str) -> Option<String> {
+        let method = self.methods.iter().find(|method| method.name.as_str() == name)?;
+        let bindings = self.self_type_display_bindings();
+
+        // Only the method's own generics are rendered, not those of the trait
+        let generics = vecmap(&method.direct_generics, |generic| generic.name.to_string());
+        let generics =
+            if generics.is_empty() { String::new() } else { format!("<{}>", generics.join(", ")) };
+
+        let arguments =
+            vecmap(method.arguments(), |typ| typ.force_substitute(&bindings).to_string());
+        let arguments = arguments.join(", ");
+
+        let signature = format!("fn {name}{generics}({arguments})");
+        match method.return_type().force_substitute(&bindings) {
+            Type::Unit => Some(signature),
+            return_type => Some(format!("{signature} -> {return_type}")),
+        }
+    }
+
+    /// Returns bindings which substitute this trait's self type variable with a `Self` generic.
+    ///
+    /// The `Self` generic reuses the id of the self type variable but is never bound, so it is
+    /// rendered as `Self` even while the self type variable is bound to a concrete type.
+    fn self_type_display_bindings(&self) -> TypeBindings {
+        let self_kind = self.self_type_typevar.kind();
+        let self_type = Type::NamedGeneric(NamedGeneric {
+            type_var: TypeVariable::unbound(self.self_type_typevar.id(), self_kind.clone()),
+            name: Rc::new("Self".to_owned()),
+            implicit: false,
+        });
+        let mut bindings = TypeBindings::default();
+        bindings.insert(
+            self.self_type_typevar.id(),
+            (self.self_type_typevar.clone(), self_kind, self_type),
+        );
+        bindings
+    }
+
+    /// Returns the names of the associated types referenced by the argument or return types
+    /// of the method with the given name, in the order they are declared in the trait.
+    ///
+    /// These are the associated types which may be inferred from a call to the method.
+    pub fn associated_types_in_method(&self, name: &str) -> Vec<Ident> {
+        let Some(method) = self.methods.iter().find(|method| method.name.as_str() == name) else {
+            return Vec::new();
+        };
+
+        self.associated_types
+            .iter()
+            .filter(|associated_type| {
+                let id = associated_type.type_var.id();
+                method.arguments().iter().any(|typ| typ.occurs(id))
+                    || method.return_type().occurs(id)
+            })
+            .map(|associated_type| {
+                Ident::new(associated_type.name.to_string(), associated_type.location)
+            })
+            .collect()
+    }
+
+    /// Returns the generics of the method with the given name which appear in its return type
+    /// but in none of its arguments, in the order they are declared.
+    ///
+    /// These generics can't be inferred from a call's arguments so they may need to be given explicitly.
+    pub fn return_only_generics(&self, method_name: &str) -> Vec<TypeVariable> {
+        let Some(method) = self.methods.iter().find(|method| method.name.as_str() == method_name)
+        else {
+            return Vec::new();
+        };
+
+        method
+            .direct_generics
+            .iter()
+            .filter(|generic| {
+                let id = generic.type_var.id();
+                method.return_type().occurs(id)
+                    && !method.arguments().iter().any(|typ| typ.occurs(id))
+            })
+            .map(|generic| generic.type_var.clone())
+            .collect()
+    }
+
+    /// Checks that every method in this trait has a corresponding entry in `method_ids`.
+    ///
+    /// Returns the names of the methods without an id otherwise.
+    pub fn validate_method_ids(&self) -> Result<(), Vec<String>> {
+        let missing: Vec<String> = self
+            .methods
+            .iter()
+            .filter(|method| !self.method_ids.contains_key(method.name.as_str()))
+            .map(|method| method.name.to_string())
+            .collect();
+        if missing.is_empty() { Ok(()) } else { Err(missing) }
+    }
+
+    /// Returns the names of the methods declared more than once in this trait, reporting each
+    /// name once at the location of its first repeated declaration.
+    pub fn duplicate_method_names(&self) -> Vec<Ident> {
+        let mut seen = HashSet::default();
+        let mut reported = HashSet::default();
+        self.methods
+            .iter()
+            .filter(|method| {
+                let name = method.name.as_str();
+                !seen.insert(name) && reported.insert(name)
+            })
+            .map(|method| method.name.clone())
+            .collect()
+    }
+
+    /// Returns the methods of this trait which are visible outside of its crate.
+    ///
+    /// Trait methods don't have their own visibility, so these are all of the methods if the trait
+    /// itself is public, and none of them otherwise.
+    pub fn public_methods(&self) -> Vec<&TraitFunction> {
+        if self.visibility == ItemVisibility::Public {
+            self.methods.iter().collect()
+        } else {
+            Vec::new()
+        }
+    }
+
     pub fn find_method_or_constant(
         &self,
         name: &str,
@@ -222,6 +353,78 @@ impl Trait {
         self.associated_constant_ids.get(name).copied()
     }
 
+    /// Returns the name, definition id and type of each associated constant of this trait, sorted by name.
+    ///
+    /// Constants whose type isn't known to the interner, or failed to resolve, are skipped.
+    pub fn associated_constants(
+        &self,
+        interner: &NodeInterner,
+    ) -> Vec<(String, DefinitionId, Type)> {
+        let mut constants: Vec<_> = self
+            .associated_constant_ids
+            .iter()
+            .filter_map(|(name, id)| {
+                let typ = interner.definition_type(*id);
+                (typ != Type::Error).then(|| (name.clone(), *id, typ))
+            })
+            .collect();
+        constants.sort_by(|(a, _, _), (b, _, _)| a.cmp(b));
+        constants
+    }
+
+    /// Returns whether the method with the given name is static, i.e. whether its first argument
+    /// is not `Self` or a reference to `Self`. Returns `None` if the trait has no such method.
+    pub fn is_static_method(&self, name: &str) -> Option<bool> {
+        let method = self.methods.iter().find(|method| method.name.as_str() == name)?;
+        let takes_self = method.arguments().first().is_some_and(|typ| match typ {
+            Type::Reference(element, _) => self.is_self_type(element),
+            typ => self.is_self_type(typ),
+        });
+        Some(!takes_self)
+    }
+
+    /// Returns whether `typ` is this trait's `Self` type variable.
+    ///
+    /// Within the trait's methods `Self` is a named generic wrapping that type variable, so both forms are accepted.
+    pub fn is_self_type(&self, typ: &Type) -> bool {
+        match typ {
+            Type::TypeVariable(type_var) | Type::NamedGeneric(NamedGeneric { type_var, .. }) => {
+                type_var.id() == self.self_type_typevar.id()
+            }
+            _ => false,
+        }
+    }
+
+    /// Returns this trait's where clause with `Self` replaced by `self_type`, i.e. the constraints
+    /// an impl of this trait for `self_type` must satisfy.
+    pub fn where_clause_for_self(&self, self_type: &Type) -> Vec<TraitConstraint> {
+        let mut bindings = TypeBindings::default();
+        bindings.insert(
+            self.self_type_typevar.id(),
+            (self.self_type_typevar.clone(), self.self_type_typevar.kind(), self_type.clone()),
+        );
+
+        let mut where_clause = self.where_clause.clone();
+        for constraint in &mut where_clause {
+            constraint.apply_bindings(&bindings);
+        }
+        where_clause
+    }
+
+    /// Returns the number of ordered generics which must be given explicitly when referring to this trait.
+    ///
+    /// Trait generics cannot have default values so this is currently always the same as
+    /// [`Trait::total_generic_arity`]. Associated types are named generics and are not counted.
+    pub fn required_generic_arity(&self) -> usize {
+        // Every ordered generic is mandatory as there is no syntax for generic defaults
+        self.generics.len()
+    }
+
+    /// Returns the number of ordered generics this trait has.
+    pub fn total_generic_arity(&self) -> usize {
+        self.generics.len()
+    }
+
     pub fn get_associated_type(&self, last_name: &str) -> Option<&ResolvedGeneric> {
         self.associated_types.iter().find(|typ| typ.name.as_ref() == last_name)
     }
@@ -243,6 +446,219 @@ impl Trait {
         TraitGenerics { ordered, named }
     }
 
+    /// Returns the ids of every trait this trait inherits from, either directly through its
+    /// trait bounds or transitively through the trait bounds of those traits.
+    ///
+    /// Each supertrait is returned once, in breadth-first order. This trait itself is never
+    /// included so that dependency cycles (which are reported elsewhere) are broken.
+    pub fn all_super_traits(&self, interner: &NodeInterner) -> Vec<TraitId> {
+        let mut visited = HashSet::default();
+        visited.insert(self.id);
+
+        let mut super_traits = Vec::new();
+        let mut queue: VecDeque<TraitId> =
+            self.trait_bounds.iter().map(|bound| bound.trait_id).collect();
+        while let Some(trait_id) = queue.pop_front() {
+            if !visited.insert(trait_id) {
+                continue;
+            }
+            super_traits.push(trait_id);
+
+            let super_trait = interner.get_trait(trait_id);
+            queue.extend(super_trait.trait_bounds.iter().map(|bound| bound.trait_id));
+        }
+        super_traits
+    }
+
+    /// Returns the id of the trait declaring the method with the given name: this trait if the
+    /// method is declared directly, otherwise the nearest super trait declaring it.
+    /// Returns `None` if no trait in the hierarchy declares the method.
+    pub fn method_origin(&self, name: &str, interner: &NodeInterner) -> Option<TraitId> {
+        let declares_method =
+            |the_trait: &Trait| the_trait.methods.iter().any(|method| method.name.as_str() == name);
+        if declares_method(self) {
+            return Some(self.id);
+        }
+
+        self.all_super_traits(interner)
+            .into_iter()
+            .find(|trait_id| declares_method(interner.get_trait(*trait_id)))
+    }
+
+    /// Returns the bounds on the associated type with the given name, together with the super trait
+    /// bounds of each bounded trait, transitively. Each bound is only returned once.
+    ///
+    /// For example for `type Item: Foo<u32>` where `trait Foo<T>: Bar<T>` this returns `Foo<u32>` and `Bar<u32>`.
+    ///
+    /// The super trait bounds are followed once per instantiation of each trait, so for `Foo<u32> + Foo<u64>`
+    /// both `Bar<u32>` and `Bar<u64>` are returned. They aren't followed for a trait reached through its own
+    /// super traits, as with cyclic super traits such as `trait Foo<T>: Foo<[T; 1]>` the bounds could
+    /// otherwise keep growing without ever repeating.
+    pub fn transitive_associated_type_bounds(
+        &self,
+        assoc_name: &str,
+        interner: &NodeInterner,
+    ) -> Vec<ResolvedTraitBound> {
+        let mut bounds: Vec<ResolvedTraitBound> = Vec::new();
+        let mut visited = HashSet::default();
+        // Each bound is queued along with the traits it was reached through
+        let mut queue: VecDeque<(ResolvedTraitBound, Rc<Vec<TraitId>>)> = self
+            .associated_type_bounds
+            .get(assoc_name)
+            .into_iter()
+            .flatten()
+            .map(|bound| (bound.clone(), Rc::default()))
+            .collect();
+        while let Some((bound, ancestors)) = queue.pop_front() {
+            if bounds.contains(&bound) {
+                continue;
+            }
+
+            let is_cyclic = ancestors.contains(&bound.trait_id);
+            if !is_cyclic && visited.insert((bound.trait_id, bound.trait_generics.to_string())) {
+                // The super trait bounds are written in terms of the bounded trait's generics
+                let bound_trait = interner.get_trait(bound.trait_id);
+                let mut bindings = TypeBindings::default();
+                for (generic, arg) in bound_trait.generics.iter().zip(&bound.trait_generics.ordered)
+                {
+                    let type_var = generic.type_var.clone();
+                    bindings.insert(type_var.id(), (type_var, generic.kind(), arg.clone()));
+                }
+                let mut super_ancestors = ancestors.as_ref().clone();
+                super_ancestors.push(bound.trait_id);
+                let super_ancestors = Rc::new(super_ancestors);
+                for super_bound in &bound_trait.trait_bounds {
+                    let mut super_bound = super_bound.clone();
+                    super_bound.apply_bindings(&bindings);
+                    queue.push_back((super_bound, super_ancestors.clone()));
+                }
+            }
+
+            bounds.push(bound);
+        }
+        bounds
+    }
+
+    /// Returns this trait's where clause followed by the where clauses of all of its supertraits,
+    /// which together are the constraints an impl of this trait must satisfy.
+    ///
+    /// Constraints which render to the same string are only returned once.
+    pub fn all_where_constraints(&self, interner: &NodeInterner) -> Vec<TraitConstraint> {
+        let super_traits = self.all_super_traits(interner);
+        let traits =
+            std::iter::once(self).chain(super_traits.iter().map(|id| interner.get_trait(*id)));
+
+        let mut seen = HashSet::default();
+        let mut constraints = Vec::new();
+        for the_trait in traits {
+            for constraint in &the_trait.where_clause {
+                if seen.insert(constraint.to_string(interner)) {
+                    constraints.push(constraint.clone());
+                }
+            }
+        }
+        constraints
+    }
+
+    /// Returns the name of this trait prefixed by the name of the crate it is defined in, e.g. `mylib::Foo`.
+    ///
+    /// A dependency is named by the name a dependent crate imports it under, which the interner records
+    /// as the name of the dependency's root module. If no such name is recorded the crate's numeric id
+    /// is used instead. The root crate is named `crate` and the standard library `std`.
+    pub fn qualified_name(&self, interner: &NodeInterner) -> String {
+        let crate_name = match self.crate_id {
+            CrateId::Root(_) | CrateId::RootAndStdlib(_) => "crate".to_string(),
+            CrateId::Stdlib(_) => "std".to_string(),
+            CrateId::Crate(id) => {
+                // Walk up to the crate's root module, the only module without a parent
+                let mut module = ModuleId { krate: self.crate_id, local_id: self.id.0.local_id };
+                let mut root_name = None;
+                while let Some(attributes) = interner.try_module_attributes(&module) {
+                    match attributes.parent {
+                        Some(parent) => module.local_id = parent,
+                        None => {
+                            root_name = Some(attributes.name.clone());
+                            break;
+                        }
+                    }
+                }
+                root_name.unwrap_or_else(|| id.to_string())
+            }
+            CrateId::Dummy => "<dummy>".to_string(),
+        };
+        format!("{crate_name}::{}", self.name)
+    }
+
+    /// Returns a hash of this trait's interface: its generics and their kinds, method signatures
+    /// and where clauses, associated types and their bounds, associated constants, trait bounds
+    /// and where clause.
+    ///
+    /// Method bodies and locations are not hashed, so the hash only changes when the interface does.
+    /// Neither is the trait's name, so traits with identical interfaces hash the same.
+    ///
+    /// The hash is the BLAKE3 hash of a textual rendering of the interface, truncated to 64 bits,
+    /// so it doesn't depend on the Rust release or the platform the compiler was built with.
+    pub fn interface_hash(&self, interner: &NodeInterner) -> u64 {
+        let self_bindings = self.self_type_display_bindings();
+        let generics_string = |generics: &Generics| {
+            let generics =
+                vecmap(generics, |generic| format!("{}: {}", generic.name, generic.kind()));
+            generics.join(", ")
+        };
+        let bound_string = |bound: &ResolvedTraitBound| {
+            let mut bound = bound.clone();
+            bound.apply_bindings(&self_bindings);
+            format!("{}{}", interner.get_trait(bound.trait_id).name, bound.trait_generics)
+        };
+        let constraints_string = |constraints: &[TraitConstraint]| {
+            let constraints = vecmap(constraints, |constraint| {
+                let typ = constraint.typ.substitute(&self_bindings);
+                format!("{typ}: {}", bound_string(&constraint.trait_bound))
+            });
+            constraints.join(", ")
+        };
+
+        let mut interface = vec![format!("generics {}", generics_string(&self.generics))];
+
+        let mut methods = vecmap(&self.methods, |method| method);
+        methods.sort_by(|a, b| a.name.as_str().cmp(b.name.as_str()));
+        for method in methods {
+            let signature = self
+                .method_signature_string(method.name.as_str())
+                .expect("method belongs to this trait");
+            interface.push(format!(
+                "{signature} generics {} where {}",
+                generics_string(&method.direct_generics),
+                constraints_string(&method.trait_constraints)
+            ));
+        }
+
+        interface.push(format!("types {}", generics_string(&self.associated_types)));
+        let mut type_bounds = vecmap(&self.associated_type_bounds, |(name, bounds)| {
+            format!("type {name}: {}", vecmap(bounds, bound_string).join(" + "))
+        });
+        type_bounds.sort();
+        interface.extend(type_bounds);
+
+        for (name, _, typ) in self.associated_constants(interner) {
+            interface.push(format!("let {name}: {}", typ.substitute(&self_bindings)));
+        }
+
+        interface.push(format!("bounds {}", vecmap(&self.trait_bounds, bound_string).join(" + ")));
+        interface.push(format!("where {}", constraints_string(&self.where_clause)));
+
+        let hash = blake3(interface.join("\n").as_bytes()).expect("BLAKE3 hashing cannot fail");
+        u64::from_le_bytes(hash[..8].try_into().expect("BLAKE3 hashes are 32 bytes long"))
+    }
+
+    /// Conservatively returns whether impls of this trait could overlap with impls of `other`.
+    ///
+    /// Only impls of the same trait can overlap, so this currently just compares trait ids.
+    /// A future version will also account for the generic parameters of the traits.
+    pub fn impls_could_overlap(&self, other: &Trait) -> bool {
+        self.id == other.id
+    }
+
     /// Returns a TraitConstraint for this trait using Self as the object
     /// type and the uninstantiated generics for any trait generics.
     pub fn as_constraint(&self, location: Location) -> TraitConstraint {
@@ -1,8 +1,41 @@
+use std::path::Path;
+
+use iter_extended::vecmap;
+use noirc_errors::Location;
+
 use crate::{
-    assert_no_errors, check_errors, check_monomorphization_error, elaborator::FrontendOptions,
-    get_program_with_options, tests::Expect,
+    assert_no_errors,
+    ast::{IntegerBitSize, ItemVisibility},
+    check_errors, check_monomorphization_error,
+    elaborator::FrontendOptions,
+    get_program_with_options,
+    graph::CrateId,
+    hir::{Context, def_map::ModuleId},
+    hir_def::traits::Trait,
+    hir_def::types::Type,
+    node_interner::ModuleAttributes,
+    shared::Signedness,
+    test_utils::get_program,
+    tests::Expect,
 };
 
+/// Compiles `src`, which is expected to have no errors, without emitting it as a test program.
+fn get_program_context(src: &str) -> Context<'_, 'static> {
+    let (_, context, errors) = get_program(src, None, Expect::Success);
+    assert!(errors.is_empty(), "Expected no errors, got: {errors:?}");
+    context
+}
+
+/// Returns the trait with the given name in the compiled program.
+fn get_trait<'a>(context: &'a Context, name: &str) -> &'a Trait {
+    context
+        .def_interner
+        .traits
+        .values()
+        .find(|the_trait| the_trait.name.as_str() == name)
+        .unwrap_or_else(|| panic!("Expected a trait named {name}"))
+}
+
 #[named]
 #[test]
 fn trait_inheritance() {
@@ -1712,3 +1745,544 @@ fn associated_constant_of_generic_type_used_in_expression() {
     "#;
     check_monomorphization_error!(src);
 }
+
+#[test]
+fn trait_all_where_constraints_includes_supertrait_where_clauses() {
+    let src = r#"
+    pub trait Baz {}
+
+    pub trait Bar<T> where T: Baz {}
+
+    pub trait Foo<T>: Bar<T> {}
+
+    fn main() {}
+    "#;
+    let context = get_program_context(src);
+    let interner = &context.def_interner;
+    let baz = get_trait(&context, "Baz");
+    let bar = get_trait(&context, "Bar");
+    let foo = get_trait(&context, "Foo");
+
+    assert_eq!(foo.all_super_traits(interner), vec![bar.id]);
+
+    let constraints = foo.all_where_constraints(interner);
+    assert_eq!(constraints.len(), 1);
+    assert_eq!(constraints[0].trait_bound.trait_id, baz.id);
+}
+
+#[test]
+fn trait_is_static_method() {
+    let src = r#"
+    pub trait Foo<T> {
+        fn new() -> Self;
+        fn get(self) -> T;
+        fn get_ref(&mut self) -> T;
+    }
+
+    fn main() {}
+    "#;
+    let context = get_program_context(src);
+    let foo = get_trait(&context, "Foo");
+
+    assert_eq!(foo.is_static_method("new"), Some(true));
+    assert_eq!(foo.is_static_method("get"), Some(false));
+    assert_eq!(foo.is_static_method("get_ref"), Some(false));
+    assert_eq!(foo.is_static_method("unknown"), None);
+}
+
+#[test]
+fn trait_validate_method_ids() {
+    let src = r#"
+    pub trait Foo {
+        fn foo(self) -> Field;
+        fn bar(self) -> Field;
+    }
+
+    fn main() {}
+    "#;
+    let mut context = get_program_context(src);
+    let foo_id = get_trait(&context, "Foo").id;
+    assert_eq!(context.def_interner.get_trait(foo_id).validate_method_ids(), Ok(()));
+
+    context.def_interner.get_trait_mut(foo_id).method_ids.remove("bar");
+    assert_eq!(
+        context.def_interner.get_trait(foo_id).validate_method_ids(),
+        Err(vec!["bar".to_string()])
+    );
+}
+
+#[test]
+fn trait_method_signature_string() {
+    let src = r#"
+    pub trait Foo<T> {
+        fn new() -> Self;
+        fn map<U, V>(self, x: U, y: [V; 3]) -> (T, U);
+        fn set(&mut self, value: T);
+    }
+
+    fn main() {}
+    "#;
+    let context = get_program_context(src);
+    let foo = get_trait(&context, "Foo");
+
+    assert_eq!(foo.method_signature_string("new"), Some("fn new() -> Self".to_string()));
+    assert_eq!(
+        foo.method_signature_string("map"),
+        Some("fn map<U, V>(Self, U, [V; 3]) -> (T, U)".to_string())
+    );
+    assert_eq!(foo.method_signature_string("set"), Some("fn set(&mut Self, T)".to_string()));
+    assert_eq!(foo.method_signature_string("unknown"), None);
+}
+
+#[test]
+fn trait_associated_types_in_method() {
+    let src = r#"
+    pub trait Container {
+        type Item;
+        type Index;
+
+        fn get(self) -> Self::Item;
+        fn set(&mut self, index: Self::Index, item: Self::Item);
+        fn len(self) -> u32;
+    }
+
+    fn main() {}
+    "#;
+    let context = get_program_context(src);
+    let container = get_trait(&context, "Container");
+    let names =
+        |method| vecmap(container.associated_types_in_method(method), |name| name.to_string());
+
+    assert_eq!(names("get"), vec!["Item"]);
+    assert_eq!(names("set"), vec!["Item", "Index"]);
+    assert!(names("len").is_empty());
+    assert!(names("unknown").is_empty());
+}
+
+#[test]
+fn trait_generic_arity() {
+    let src = r#"
+    pub trait Foo<T, let N: u32> {
+        type Item;
+
+        fn foo(self) -> [T; N];
+    }
+
+    pub trait Bar {
+        fn bar(self);
+    }
+
+    fn main() {}
+    "#;
+    let context = get_program_context(src);
+
+    // Generic defaults aren't supported so every ordered generic is required
+    let foo = get_trait(&context, "Foo");
+    assert_eq!(foo.required_generic_arity(), 2);
+    assert_eq!(foo.total_generic_arity(), 2);
+
+    let bar = get_trait(&context, "Bar");
+    assert_eq!(bar.required_generic_arity(), 0);
+    assert_eq!(bar.total_generic_arity(), 0);
+}
+
+#[test]
+fn trait_method_origin() {
+    let src = r#"
+    pub trait Baz {
+        fn baz(self);
+    }
+
+    pub trait Bar: Baz {
+        fn bar(self);
+    }
+
+    pub trait Foo: Bar {
+        fn foo(self);
+    }
+
+    fn main() {}
+    "#;
+    let context = get_program_context(src);
+    let interner = &context.def_interner;
+    let foo = get_trait(&context, "Foo");
+    let bar = get_trait(&context, "Bar");
+    let baz = get_trait(&context, "Baz");
+
+    assert_eq!(foo.method_origin("foo", interner), Some(foo.id));
+    assert_eq!(foo.method_origin("bar", interner), Some(bar.id));
+    assert_eq!(foo.method_origin("baz", interner), Some(baz.id));
+    assert_eq!(bar.method_origin("foo", interner), None);
+}
+
+#[test]
+fn trait_transitive_associated_type_bounds() {
+    let src = r#"
+    pub trait Inner<T> {}
+    pub trait Outer<T>: Inner<T> {}
+    pub trait Other {}
+
+    pub trait Foo {
+        type Item: Outer<u32> + Other;
+        type Unbounded;
+    }
+
+    fn main() {}
+    "#;
+    let context = get_program_context(src);
+    let interner = &context.def_interner;
+    let foo = get_trait(&context, "Foo");
+    let outer = get_trait(&context, "Outer");
+    let inner = get_trait(&context, "Inner");
+    let other = get_trait(&context, "Other");
+
+    let bounds = foo.transitive_associated_type_bounds("Item", interner);
+    let trait_ids = vecmap(&bounds, |bound| bound.trait_id);
+    assert_eq!(trait_ids, vec![outer.id, other.id, inner.id]);
+
+    // The super trait bound is instantiated with the generics of the bound it came from
+    assert_eq!(bounds[2].trait_generics.ordered[0].to_string(), "u32");
+
+    assert!(foo.transitive_associated_type_bounds("Unbounded", interner).is_empty());
+}
+
+#[test]
+fn trait_transitive_associated_type_bounds_with_several_instantiations() {
+    let src = r#"
+    pub trait Inner<T> {}
+    pub trait Outer<T>: Inner<T> {}
+
+    pub trait Foo {
+        type Item: Outer<u32> + Outer<u64>;
+    }
+
+    fn main() {}
+    "#;
+    let context = get_program_context(src);
+    let foo = get_trait(&context, "Foo");
+    let outer = get_trait(&context, "Outer");
+    let inner = get_trait(&context, "Inner");
+
+    let bounds = foo.transitive_associated_type_bounds("Item", &context.def_interner);
+    let trait_ids = vecmap(&bounds, |bound| bound.trait_id);
+    assert_eq!(trait_ids, vec![outer.id, outer.id, inner.id, inner.id]);
+    assert_eq!(bounds[2].trait_generics.ordered[0].to_string(), "u32");
+    assert_eq!(bounds[3].trait_generics.ordered[0].to_string(), "u64");
+}
+
+#[test]
+fn trait_transitive_associated_type_bounds_with_growing_cycle() {
+    let src = r#"
+    pub trait Grow<T>: Grow<[T; 1]> {}
+
+    pub trait Foo {
+        type Item: Grow<u32>;
+    }
+
+    fn main() {}
+    "#;
+    let (_, context, _) = get_program(src, None, Expect::Error);
+    let foo = get_trait(&context, "Foo");
+    let grow = get_trait(&context, "Grow");
+
+    let bounds = foo.transitive_associated_type_bounds("Item", &context.def_interner);
+    let trait_ids = vecmap(&bounds, |bound| bound.trait_id);
+    assert_eq!(trait_ids, vec![grow.id, grow.id]);
+    assert_eq!(bounds[1].trait_generics.ordered[0].to_string(), "[u32; 1]");
+}
+
+#[test]
+fn trait_duplicate_method_names() {
+    let src = r#"
+    pub trait Foo {
+        fn foo(self);
+        fn bar(self);
+        fn foo(self, x: Field);
+    }
+
+    pub trait Bar {
+        fn foo(self);
+        fn bar(self);
+    }
+
+    fn main() {}
+    "#;
+    let (_, context, errors) = get_program(src, None, Expect::Error);
+    assert!(!errors.is_empty());
+    let foo = get_trait(&context, "Foo");
+    let bar = get_trait(&context, "Bar");
+
+    let duplicates = vecmap(foo.duplicate_method_names(), |name| name.to_string());
+    assert_eq!(duplicates, vec!["foo".to_string()]);
+    assert!(bar.duplicate_method_names().is_empty());
+}
+
+#[test]
+fn trait_qualified_name() {
+    let src = r#"
+    mod foo {
+        pub trait Foo {}
+    }
+
+    fn main() {}
+    "#;
+    let mut context = get_program_context(src);
+    assert_eq!(get_trait(&context, "Foo").qualified_name(&context.def_interner), "crate::Foo");
+
+    // Move the trait into a dependency of the root crate
+    let lib_file_id = context
+        .file_manager
+        .to_mut()
+        .add_file_with_source(Path::new("lib.nr"), String::new())
+        .unwrap();
+    let lib_crate_id = context.crate_graph.add_crate(lib_file_id);
+    let foo =
+        context.def_interner.traits.values_mut().find(|the_trait| the_trait.name.as_str() == "Foo");
+    let foo = foo.unwrap();
+    foo.crate_id = lib_crate_id;
+    let foo_module = foo.id.0.local_id;
+    let CrateId::Crate(lib_id) = lib_crate_id else { panic!("expected a dependency crate id") };
+    assert_eq!(
+        get_trait(&context, "Foo").qualified_name(&context.def_interner),
+        format!("{lib_id}::Foo")
+    );
+
+    // Once the dependency's modules are known, the name of its root module is used
+    let root_module = context.def_map(context.root_crate_id()).unwrap().root();
+    let module_attributes = |name: &str, parent| ModuleAttributes {
+        name: name.to_string(),
+        location: Location::dummy(),
+        parent,
+        visibility: ItemVisibility::Public,
+    };
+    context.def_interner.add_module_attributes(
+        ModuleId { krate: lib_crate_id, local_id: foo_module },
+        module_attributes("foo", Some(root_module)),
+    );
+    context.def_interner.add_module_attributes(
+        ModuleId { krate: lib_crate_id, local_id: root_module },
+        module_attributes("mylib", None),
+    );
+    assert_eq!(get_trait(&context, "Foo").qualified_name(&context.def_interner), "mylib::Foo");
+}
+
+#[test]
+fn trait_public_methods() {
+    let src = r#"
+    pub trait Foo {
+        fn foo(self);
+        fn bar(self);
+    }
+
+    trait Bar {
+        fn bar(self);
+    }
+
+    impl Bar for Field {
+        fn bar(self) {}
+    }
+
+    fn main() {
+        let x: Field = 1;
+        x.bar();
+    }
+    "#;
+    let context = get_program_context(src);
+    let foo = get_trait(&context, "Foo");
+    let bar = get_trait(&context, "Bar");
+
+    let public_methods = vecmap(foo.public_methods(), |method| method.name.to_string());
+    assert_eq!(public_methods, vec!["foo".to_string(), "bar".to_string()]);
+    assert!(bar.public_methods().is_empty());
+}
+
+#[test]
+fn trait_impls_could_overlap() {
+    let src = r#"
+    pub trait Foo {}
+    pub trait Bar {}
+
+    fn main() {}
+    "#;
+    let context = get_program_context(src);
+    let foo = get_trait(&context, "Foo");
+    let bar = get_trait(&context, "Bar");
+
+    assert!(foo.impls_could_overlap(foo));
+    assert!(!foo.impls_could_overlap(bar));
+    assert!(!bar.impls_could_overlap(foo));
+}
+
+#[test]
+fn trait_associated_constants() {
+    let src = r#"
+    pub trait Foo {
+        let N: u32;
+        let M: Field;
+    }
+
+    fn main() {}
+    "#;
+    let context = get_program_context(src);
+    let interner = &context.def_interner;
+    let foo = get_trait(&context, "Foo");
+
+    let constants = vecmap(foo.associated_constants(interner), |(name, id, typ)| {
+        assert_eq!(foo.associated_constant_ids[&name], id);
+        (name, typ.to_string())
+    });
+    assert_eq!(
+        constants,
+        vec![("M".to_string(), "Field".to_string()), ("N".to_string(), "u32".to_string())]
+    );
+}
+
+#[test]
+fn trait_is_self_type() {
+    let src = r#"
+    pub trait Foo {
+        fn foo(self, x: Field);
+    }
+
+    pub trait Bar {}
+
+    fn main() {}
+    "#;
+    let context = get_program_context(src);
+    let foo = get_trait(&context, "Foo");
+    let bar = get_trait(&context, "Bar");
+
+    let self_type = Type::TypeVariable(foo.self_type_typevar.clone());
+    assert!(foo.is_self_type(&self_type));
+    assert!(!bar.is_self_type(&self_type));
+
+    let arguments = foo.methods[0].arguments();
+    assert!(foo.is_self_type(&arguments[0]));
+    assert!(!foo.is_self_type(&arguments[1]));
+    assert!(!foo.is_self_type(&Type::FieldElement));
+}
+
+#[test]
+fn trait_return_only_generics() {
+    let src = r#"
+    pub trait Foo {
+        fn make<T>() -> T;
+        fn convert<T, U>(x: T) -> (T, U);
+        fn id<T>(x: T) -> T;
+    }
+
+    fn main() {}
+    "#;
+    let context = get_program_context(src);
+    let foo = get_trait(&context, "Foo");
+
+    let generic_id =
+        |method: usize, index: usize| foo.methods[method].direct_generics[index].type_var.id();
+    assert_eq!(vecmap(foo.return_only_generics("make"), |typ| typ.id()), vec![generic_id(0, 0)]);
+    assert_eq!(vecmap(foo.return_only_generics("convert"), |typ| typ.id()), vec![generic_id(1, 1)]);
+    assert!(foo.return_only_generics("id").is_empty());
+    assert!(foo.return_only_generics("missing").is_empty());
+}
+
+#[test]
+fn trait_where_clause_for_self() {
+    let src = r#"
+    pub trait Clone {
+        fn clone(self) -> Self;
+    }
+
+    pub trait Foo where Self: Clone {}
+
+    fn main() {}
+    "#;
+    let context = get_program_context(src);
+    let foo = get_trait(&context, "Foo");
+    let clone = get_trait(&context, "Clone");
+
+    let u32_type = Type::Integer(Signedness::Unsigned, IntegerBitSize::ThirtyTwo);
+    let where_clause = foo.where_clause_for_self(&u32_type);
+    assert_eq!(where_clause.len(), 1);
+    assert_eq!(where_clause[0].typ, u32_type);
+    assert_eq!(where_clause[0].trait_bound.trait_id, clone.id);
+    assert_eq!(where_clause[0].to_string(&context.def_interner), "u32: Clone");
+
+    // The trait's own where clause is left untouched
+    assert!(foo.is_self_type(&foo.where_clause[0].typ));
+}
+
+#[test]
+fn trait_interface_hash_ignores_default_bodies() {
+    let src = r#"
+    pub trait Foo {
+        fn foo(_self: Self) -> Field { 1 }
+        fn bar<T>(x: T);
+    }
+
+    pub trait Bar {
+        fn foo(_self: Self) -> Field { 2 }
+        fn bar<T>(x: T);
+    }
+
+    pub trait Baz {
+        fn foo(_self: Self) -> Field { 1 }
+        fn bar<T>(x: T);
+        fn baz(self);
+    }
+
+    fn main() {}
+    "#;
+    let context = get_program_context(src);
+    let interner = &context.def_interner;
+    let foo = get_trait(&context, "Foo").interface_hash(interner);
+    let bar = get_trait(&context, "Bar").interface_hash(interner);
+    let baz = get_trait(&context, "Baz").interface_hash(interner);
+
+    assert_eq!(foo, bar);
+    assert_ne!(foo, baz);
+}
+
+#[test]
+fn trait_interface_hash_covers_bounds_constraints_and_kinds() {
+    let src = r#"
+    pub trait A {}
+    pub trait B {}
+
+    pub trait TypeBoundA {
+        type Item: A;
+    }
+
+    pub trait TypeBoundB {
+        type Item: B;
+    }
+
+    pub trait MethodWhereA {
+        fn foo<T>(x: T) where T: A;
+    }
+
+    pub trait MethodWhereB {
+        fn foo<T>(x: T) where T: B;
+    }
+
+    pub trait KindU32<let N: u32> {}
+
+    pub trait KindU8<let N: u8> {}
+
+    pub trait MethodKindU32 {
+        fn foo<let N: u32>();
+    }
+
+    pub trait MethodKindU8 {
+        fn foo<let N: u8>();
+    }
+
+    fn main() {}
+    "#;
+    let context = get_program_context(src);
+    let interner = &context.def_interner;
+    let hash = |name: &str| get_trait(&context, name).interface_hash(interner);
+
+    assert_ne!(hash("TypeBoundA"), hash("TypeBoundB"));
+    assert_ne!(hash("MethodWhereA"), hash("MethodWhereB"));
+    assert_ne!(hash("KindU32"), hash("KindU8"));
+    assert_ne!(hash("MethodKindU32"), hash("MethodKindU8"));
+}